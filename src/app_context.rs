@@ -1,23 +1,43 @@
-use std::{sync::Arc, thread::sleep, time::Duration};
+use std::{collections::HashMap, sync::Arc, thread::sleep, time::Duration};
 
 use cfd_engine_sb_contracts::{
     PendingOrderNeedApproveEvent, PendingPositionPersistenceEvent, PositionManagerPositionMarginCallHit, PositionPersistenceEvent, PositionToppingUpEvent
 };
 use service_sdk::{
     my_service_bus::abstractions::publisher::MyServiceBusPublisher,
-    my_telemetry::MyTelemetryContext, rust_extensions::{AppStates, StopWatch}, ServiceContext,
+    my_telemetry::MyTelemetryContext, rust_extensions::{date_time::DateTimeAsMicroseconds, AppStates, StopWatch}, ServiceContext,
 };
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
-use crate::{PositionManagerPersistenceClient, SettingsReader};
+use crate::{
+    AccountModes, AccountsCache, IdempotencyCache, PendingConfirmationsJournal,
+    PendingExpirationsCache, PersistenceOutbox, PositionManagerPersistenceClient,
+    PositionUpdatesHub, QuoteAgeLimits, RejectPendingAction, SettingsReader, TickFilter,
+};
 
 use trading_sdk::mt_engine::{ActivePositionsCache, MtBidAskCache, PendingPositionsCache};
 
+pub const DEFAULT_OUTBOX_JOURNAL_PATH: &str = "./outbox.journal";
+pub const DEFAULT_IDEMPOTENCY_TTL_SEC: u64 = 300;
+pub const DEFAULT_PENDING_CONFIRMATION_TIMEOUT_SEC: u64 = 30;
+pub const DEFAULT_PENDING_CONFIRMATIONS_JOURNAL_PATH: &str = "./pending_confirmations.journal";
+
 pub struct AppContext {
     pub active_positions_cache: Arc<RwLock<ActivePositionsCache>>,
     pub pending_execute_to_confirm_positions: Arc<RwLock<PendingPositionsCache>>,
     pub pending_positions_cache: Arc<RwLock<PendingPositionsCache>>,
+    pub pending_expirations: Mutex<PendingExpirationsCache>,
+    pub pending_confirmation_deadlines: Mutex<PendingExpirationsCache>,
+    pub pending_confirmations_journal: Mutex<PendingConfirmationsJournal>,
+    pub pending_confirmation_timeout: Duration,
+    pub pending_confirmation_timeout_action: RejectPendingAction,
     pub active_prices_cache: Arc<RwLock<MtBidAskCache>>,
+    pub quote_age_limits: QuoteAgeLimits,
+    pub last_quote_dates: Mutex<HashMap<String, DateTimeAsMicroseconds>>,
+    pub tick_filter: Mutex<TickFilter>,
+    pub accounts_cache: Mutex<AccountsCache>,
+    pub cross_margin_stop_out_level: Option<f64>,
+    pub position_updates_hub: Mutex<PositionUpdatesHub>,
     pub app_states: Arc<AppStates>,
     pub active_positions_persistence_publisher: MyServiceBusPublisher<PositionPersistenceEvent>,
     pub pending_need_confirm_publisher: MyServiceBusPublisher<PendingOrderNeedApproveEvent>,
@@ -25,25 +45,128 @@ pub struct AppContext {
         MyServiceBusPublisher<PendingPositionPersistenceEvent>,
     pub margin_call_publisher: MyServiceBusPublisher<PositionManagerPositionMarginCallHit>,
     pub topping_up_publisher: MyServiceBusPublisher<PositionToppingUpEvent>,
+    pub persistence_outbox: PersistenceOutbox,
+    pub idempotency_cache: IdempotencyCache,
+    pub account_modes: AccountModes,
     pub debug: bool,
 }
 
 impl AppContext {
     pub async fn new(settings: &Arc<SettingsReader>, service_context: &ServiceContext) -> Self {
-        let (active_prices_cache, active_positions_cache, pending_positions_cache) =
-            load_data(settings).await;
+        let settings_model = settings.get_settings().await;
+
+        if settings_model.cross_margin_stop_out_level.is_some() {
+            println!(
+                "Account balances are not persisted, the cross margin stop out of an account starts once its balance is sent again"
+            );
+        }
+
+        let mut pending_confirmations_journal = PendingConfirmationsJournal::new(Some(
+            settings_model
+                .pending_confirmations_journal_path
+                .as_deref()
+                .unwrap_or(DEFAULT_PENDING_CONFIRMATIONS_JOURNAL_PATH),
+        ));
+
+        let persistence_outbox = PersistenceOutbox::new(Some(
+            settings_model
+                .outbox_journal_path
+                .as_deref()
+                .unwrap_or(DEFAULT_OUTBOX_JOURNAL_PATH),
+        ));
+
+        let (
+            active_prices_cache,
+            active_positions_cache,
+            pending_positions_cache,
+            pending_expirations,
+            pending_execute_to_confirm_positions,
+        ) = load_data(
+            settings,
+            &persistence_outbox,
+            &pending_confirmations_journal.get_ids(),
+        )
+        .await;
+
+        let pending_confirmation_timeout = Duration::from_secs(
+            settings_model
+                .pending_confirmation_timeout_sec
+                .unwrap_or(DEFAULT_PENDING_CONFIRMATION_TIMEOUT_SEC),
+        );
+
+        // Restored orders get a full timeout, the approver has to see them again anyway.
+        let mut pending_confirmation_deadlines = PendingExpirationsCache::new();
+        let deadline = DateTimeAsMicroseconds::now().unix_microseconds
+            + pending_confirmation_timeout.as_micros() as i64;
+
+        for id in pending_confirmations_journal.get_ids() {
+            if pending_execute_to_confirm_positions.0.get_by_id(&id).is_some() {
+                pending_confirmation_deadlines.arm(&id, deadline);
+            } else {
+                pending_confirmations_journal.remove(&id);
+            }
+        }
 
         Self {
             active_prices_cache,
             pending_positions_cache,
             active_positions_cache,
-            pending_execute_to_confirm_positions: Arc::new(RwLock::new(PendingPositionsCache::new())),
+            pending_expirations: Mutex::new(pending_expirations),
+            pending_confirmation_deadlines: Mutex::new(pending_confirmation_deadlines),
+            pending_confirmations_journal: Mutex::new(pending_confirmations_journal),
+            pending_confirmation_timeout,
+            // Returning to pending would trigger the order again right away, so without an
+            // approver the only safe default is to cancel.
+            pending_confirmation_timeout_action: settings_model
+                .pending_confirmation_timeout_action
+                .as_deref()
+                .map(|x| {
+                    RejectPendingAction::parse(x).unwrap_or_else(|| {
+                        panic!("Invalid pending_confirmation_timeout_action: {}", x)
+                    })
+                })
+                .unwrap_or(RejectPendingAction::Cancel),
+            pending_execute_to_confirm_positions: Arc::new(RwLock::new(
+                pending_execute_to_confirm_positions,
+            )),
+            quote_age_limits: QuoteAgeLimits::new(
+                settings_model.max_quote_age_sec,
+                &settings_model
+                    .max_quote_age_sec_by_instrument
+                    .clone()
+                    .unwrap_or_default(),
+            ),
+            last_quote_dates: Mutex::new(HashMap::new()),
+            tick_filter: Mutex::new(TickFilter::new(
+                settings_model.tick_filter.clone().unwrap_or_default(),
+                settings_model
+                    .tick_filter_by_instrument
+                    .clone()
+                    .unwrap_or_default(),
+            )),
+            accounts_cache: Mutex::new(AccountsCache::new()),
+            cross_margin_stop_out_level: settings_model.cross_margin_stop_out_level,
+            position_updates_hub: Mutex::new(PositionUpdatesHub::new()),
             app_states: Arc::new(AppStates::create_initialized()),
             active_positions_persistence_publisher: service_context.get_sb_publisher(false).await,
             pending_positions_persistence_publisher: service_context.get_sb_publisher(false).await,
             margin_call_publisher: service_context.get_sb_publisher(false).await,
             topping_up_publisher: service_context.get_sb_publisher(false).await,
             pending_need_confirm_publisher: service_context.get_sb_publisher(false).await,
+            persistence_outbox,
+            idempotency_cache: IdempotencyCache::new(Duration::from_secs(
+                settings_model
+                    .idempotency_ttl_sec
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_TTL_SEC),
+            )),
+            account_modes: AccountModes::new(
+                settings_model.netting_accounts.as_deref().unwrap_or_default(),
+                settings_model
+                    .netting_account_groups
+                    .as_deref()
+                    .unwrap_or_default(),
+                settings_model.account_groups.clone().unwrap_or_default(),
+            ),
             debug: std::env::var("DEBUG").is_ok(),
         }
     }
@@ -51,10 +174,14 @@ impl AppContext {
 
 async fn load_data(
     settings: &Arc<SettingsReader>,
+    persistence_outbox: &PersistenceOutbox,
+    awaiting_confirmation_ids: &[String],
 ) -> (
     Arc<RwLock<MtBidAskCache>>,
     Arc<RwLock<ActivePositionsCache>>,
     Arc<RwLock<PendingPositionsCache>>,
+    PendingExpirationsCache,
+    PendingPositionsCache,
 ) {
     let mut sw = StopWatch::new();
     sw.start();
@@ -65,18 +192,35 @@ async fn load_data(
     telemetry.start_event_tracking("Load start data");
     let active_prices_cache = crate::flows::load_prices_cache(&grpc_client, &telemetry).await;
 
-    let (positions_cache, pending_positions_cache) = {
+    let (positions_cache, (mut pending_positions_cache, pending_expirations)) = {
         let prices_reed = &active_prices_cache.read().await;
 
-        let positions_cache =
+        let mut positions_cache =
             crate::flows::load_positions(&grpc_client, &prices_reed, &telemetry).await;
 
-        let pending_positions_cache =
+        let (mut pending_positions_cache, mut pending_expirations) =
             crate::flows::load_pending_positions(&grpc_client, &prices_reed, &telemetry).await;
 
-        (positions_cache, pending_positions_cache)
+        crate::flows::apply_outbox_journal(
+            persistence_outbox,
+            &prices_reed,
+            &mut positions_cache,
+            &mut pending_positions_cache,
+            &mut pending_expirations,
+        )
+        .await;
+
+        (
+            positions_cache,
+            (pending_positions_cache, pending_expirations),
+        )
     };
 
+    let awaiting_confirmation_cache = crate::flows::restore_awaiting_confirmation(
+        &mut pending_positions_cache,
+        awaiting_confirmation_ids,
+    );
+
     sw.pause();
 
     println!("Data loaded in: {} ms", sw.duration().as_millis());
@@ -85,5 +229,7 @@ async fn load_data(
         active_prices_cache,
         Arc::new(RwLock::new(positions_cache)),
         Arc::new(RwLock::new(pending_positions_cache)),
+        pending_expirations,
+        awaiting_confirmation_cache,
     );
 }
@@ -1,11 +1,15 @@
 use std::{collections::HashSet, sync::Arc};
 
-use cfd_engine_sb_contracts::{BidAskSbModel, PositionManagerPositionMarginCallHit};
+use cfd_engine_sb_contracts::{
+    BidAskSbModel, PendingOrderCancelReason, PendingPositionPersistenceEvent,
+    PositionManagerPositionMarginCallHit, PositionPersistenceEvent,
+};
 use service_sdk::{
     my_service_bus::abstractions::subscriber::{
         MessagesReader, MySbSubscriberHandleError, SubscriberCallback,
     },
     my_telemetry::MyTelemetryContext,
+    rust_extensions::date_time::DateTimeAsMicroseconds,
 };
 use stopwatch::Stopwatch;
 use trading_sdk::{
@@ -19,7 +23,10 @@ use trading_sdk::{
 
 use crate::{
     close_position_background, handle_pending_rdy_to_execute, handle_position_margin_call,
-    map_bid_ask, process_topping_up_refund, AppContext,
+    is_pending_group_dormant, map_active_to_sb_model, map_bid_ask, map_pending_to_sb_model,
+    process_topping_up_refund, publish_position_updates, resolve_oco_groups,
+    update_accounts_margin, update_break_even_stop, update_trailing_stop, AppContext,
+    OutboxMessage, PositionUpdate,
 };
 
 pub struct PricesListener {
@@ -48,6 +55,28 @@ impl SubscriberCallback<BidAskSbModel> for PricesListener {
                 println!("BidAsk: {:?}", operation)
             }
 
+            // Rejected ticks never reach the caches, a bad quote could stop out real positions.
+            {
+                let mut tick_filter = self.app.tick_filter.lock().await;
+
+                if let Err(reason) = tick_filter.check(&operation) {
+                    service_sdk::metrics::counter!("bid_ask_messages_rejected", "bid_ask" => asset_id.clone(), "reason" => reason.as_str())
+                        .increment(1);
+
+                    let now = DateTimeAsMicroseconds::now().unix_microseconds;
+                    if let Some(rejected) = tick_filter.take_rejects_to_log(now) {
+                        println!(
+                            "{} BidAsk ticks quarantined since last report, last one {}: {} {:?}",
+                            rejected,
+                            asset_id,
+                            reason.as_str(),
+                            operation
+                        );
+                    }
+                    continue;
+                }
+            }
+
             let telemetry = message.my_telemetry.engage_telemetry();
             message
                 .my_telemetry
@@ -70,6 +99,17 @@ pub enum UpdatePositionCase {
     ReturnToppingUp(PositionsReturnToppingUp),
 }
 
+pub struct PositionTickUpdate {
+    pub case: Option<UpdatePositionCase>,
+    pub stop_loss_move: Option<StopLossMove>,
+}
+
+pub struct StopLossMove {
+    pub position: MtPosition<MtPositionActiveState>,
+    pub is_break_even: bool,
+    pub is_trailing_stop: bool,
+}
+
 pub struct PositionsReturnToppingUp {
     pub id: String,
     pub trader_id: String,
@@ -97,6 +137,19 @@ async fn handle_bid_ask_message(
     let process_id = format!("bg-bidask-processing.{}", bid_ask.date.unix_microseconds);
     handle_prices_update_bid_ask(app.as_ref(), bid_ask.clone()).await;
     handle_active_positions_update_bid_ask(app, &bid_ask, &process_id, telemetry).await;
+
+    // A quote delivered late must not trigger pending orders. Active positions are still
+    // processed so that stop outs are not delayed, stop loss and take profit wait for a fresh
+    // quote though.
+    if app
+        .quote_age_limits
+        .is_quote_stale(&bid_ask, DateTimeAsMicroseconds::now())
+    {
+        service_sdk::metrics::counter!("stale_bid_ask_messages", "bid_ask" => bid_ask.asset_pair.clone())
+            .increment(1);
+        return;
+    }
+
     handle_pending_positions_update(app, &bid_ask, &process_id, telemetry).await;
 }
 
@@ -104,10 +157,68 @@ pub async fn handle_prices_update_bid_ask(app: &AppContext, bid_ask: MtBidAsk) {
     if app.debug {
         println!("Handle prices")
     }
+    app.last_quote_dates
+        .lock()
+        .await
+        .insert(bid_ask.asset_pair.clone(), bid_ask.date);
+
     let mut prices = app.active_prices_cache.write().await;
     prices.handle_new(bid_ask);
 }
 
+fn get_update_position_case(
+    position: &mut MtPosition<MtPositionActiveState>,
+    is_stale_quote: bool,
+) -> Option<UpdatePositionCase> {
+    let close_reason = match get_close_reason(&position) {
+        Some(MtPositionCloseReason::StopLoss) | Some(MtPositionCloseReason::TakeProfit)
+            if is_stale_quote =>
+        {
+            None
+        }
+        close_reason => close_reason,
+    };
+    if let Some(cr) = close_reason {
+        let close_dto = PositionsToCloseDto {
+            trader_id: position.base_data.trader_id.clone(),
+            account_id: position.base_data.account_id.clone(),
+            id: position.base_data.id.clone(),
+            close_reason: cr,
+        };
+
+        return Some(UpdatePositionCase::Close(close_dto));
+    };
+
+    if let Some(margin_call_percent) = position.base_data.margin_call_percent.clone() {
+        if update_margin_call_hit(position) {
+            return Some(UpdatePositionCase::MarginCallHit(
+                PositionManagerPositionMarginCallHit {
+                    position_id: position.base_data.id.clone(),
+                    trader_id: position.base_data.trader_id.clone(),
+                    account_id: position.base_data.account_id.clone(),
+                    margin_call_percent,
+                    topping_up_amount: calculate_position_topping_up(&position.base_data),
+                },
+            ));
+        };
+    };
+
+    if let Some(topping_up_amount) = calculate_position_topping_up(&position.base_data) {
+        if can_return_topping_up_funds(position) {
+            return Some(UpdatePositionCase::ReturnToppingUp(
+                PositionsReturnToppingUp {
+                    id: position.base_data.id.clone(),
+                    trader_id: position.base_data.trader_id.clone(),
+                    account_id: position.base_data.account_id.clone(),
+                    topping_up_amount,
+                },
+            ));
+        }
+    }
+
+    return None;
+}
+
 pub async fn handle_active_positions_update_bid_ask(
     app: &Arc<AppContext>,
     bid_ask: &MtBidAsk,
@@ -134,50 +245,38 @@ pub async fn handle_active_positions_update_bid_ask(
         .with_quote(&bid_ask.base)
         .with_collateral(&bid_ask.quote);
 
+    let is_stale_quote = app
+        .quote_age_limits
+        .is_quote_stale(bid_ask, DateTimeAsMicroseconds::now());
+
+    // The stop is moved in the cache before any other case is detected, so the move is carried
+    // along with whichever case wins and is persisted even when no other case fired.
     let update_function = |position: &mut MtPosition<MtPositionActiveState>| {
         update_active_position_rate(position, &bid_ask);
         update_position_pl(position);
-
-        let close_reason = get_close_reason(&position);
-        if let Some(cr) = close_reason {
-            let close_dto = PositionsToCloseDto {
-                trader_id: position.base_data.trader_id.clone(),
-                account_id: position.base_data.account_id.clone(),
-                id: position.base_data.id.clone(),
-                close_reason: cr,
-            };
-
-            return Some(UpdatePositionCase::Close(close_dto));
-        };
-
-        if let Some(margin_call_percent) = position.base_data.margin_call_percent.clone() {
-            if update_margin_call_hit(position) {
-                return Some(UpdatePositionCase::MarginCallHit(
-                    PositionManagerPositionMarginCallHit {
-                        position_id: position.base_data.id.clone(),
-                        trader_id: position.base_data.trader_id.clone(),
-                        account_id: position.base_data.account_id.clone(),
-                        margin_call_percent,
-                        topping_up_amount: calculate_position_topping_up(&position.base_data),
-                    },
-                ));
-            };
+        let is_break_even = update_break_even_stop(position, process_id);
+        let is_trailing_stop = update_trailing_stop(position, process_id);
+
+        let case = get_update_position_case(position, is_stale_quote);
+
+        let stop_loss_move = if is_break_even || is_trailing_stop {
+            Some(StopLossMove {
+                position: position.clone(),
+                is_break_even,
+                is_trailing_stop,
+            })
+        } else {
+            None
         };
 
-        if let Some(topping_up_amount) = calculate_position_topping_up(&position.base_data) {
-            if can_return_topping_up_funds(position) {
-                return Some(UpdatePositionCase::ReturnToppingUp(
-                    PositionsReturnToppingUp {
-                        id: position.base_data.id.clone(),
-                        trader_id: position.base_data.trader_id.clone(),
-                        account_id: position.base_data.account_id.clone(),
-                        topping_up_amount,
-                    },
-                ));
-            }
+        if case.is_none() && stop_loss_move.is_none() {
+            return None;
         }
 
-        return None;
+        return Some(PositionTickUpdate {
+            case,
+            stop_loss_move,
+        });
     };
 
     {
@@ -197,11 +296,56 @@ pub async fn handle_active_positions_update_bid_ask(
         update_positions_result.extend(
             write
                 .0
-                .update_positions(quote_collateral_query, update_function),
+                .update_positions(quote_collateral_query.clone(), update_function),
         );
 
         for update in update_positions_result {
-            match update {
+            if let Some(stop_loss_move) = update.stop_loss_move {
+                let position = stop_loss_move.position;
+
+                trade_log::trade_log!(
+                    &position.base_data.trader_id,
+                    &position.base_data.account_id,
+                    process_id,
+                    &position.base_data.id,
+                    "Stop loss moved.",
+                    telemetry.clone(),
+                    "sl_price" = &position.base_data.sl_price,
+                    "is_break_even" = &stop_loss_move.is_break_even,
+                    "is_trailing_stop" = &stop_loss_move.is_trailing_stop
+                );
+
+                publish_position_updates(app, &[PositionUpdate::SlTpChanged(&position)]).await;
+
+                // A close or a topping up refund persists the position state on its own, the
+                // moved stop is part of it.
+                let is_persisted_by_case = matches!(
+                    update.case,
+                    Some(UpdatePositionCase::Close(_))
+                        | Some(UpdatePositionCase::ReturnToppingUp(_))
+                );
+
+                if !is_persisted_by_case {
+                    app.persistence_outbox
+                        .enqueue(
+                            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                                process_id: process_id.to_string(),
+                                update_position: Some(map_active_to_sb_model(position)),
+                                close_position: None,
+                                create_position: None,
+                            }),
+                            Some(telemetry),
+                        )
+                        .await;
+                }
+            }
+
+            let case = match update.case {
+                Some(case) => case,
+                None => continue,
+            };
+
+            match case {
                 UpdatePositionCase::Close(close_position) => {
                     let close_result = close_position_background(
                         app,
@@ -240,6 +384,12 @@ pub async fn handle_active_positions_update_bid_ask(
                         telemetry.clone(),
                     );
                     margin_call_hit_list.insert(margin_call_hit.position_id.clone());
+
+                    if let Some(position) = write.0.get_by_id(&margin_call_hit.position_id) {
+                        publish_position_updates(app, &[PositionUpdate::MarginCall(position)])
+                            .await;
+                    }
+
                     handle_position_margin_call(app.clone(), margin_call_hit).await;
                 }
                 UpdatePositionCase::ReturnToppingUp(topping_up_return) => {
@@ -271,6 +421,30 @@ pub async fn handle_active_positions_update_bid_ask(
                 }
             }
         }
+
+        // Accounts are checked after the positions, so the ones stopped out on their own are
+        // no longer part of the aggregate.
+        let positions: Vec<_> = [
+            base_quote_query,
+            base_collateral_query,
+            quote_collateral_query,
+        ]
+        .into_iter()
+        .flat_map(|query| write.0.query_positions(query))
+        .collect();
+
+        let profit_updates: Vec<_> = positions
+            .iter()
+            .map(|x| PositionUpdate::ProfitChanged(*x))
+            .collect();
+        publish_position_updates(app, &profit_updates).await;
+
+        let accounts: HashSet<(String, String)> = positions
+            .into_iter()
+            .map(|x| (x.base_data.trader_id.clone(), x.base_data.account_id.clone()))
+            .collect();
+
+        update_accounts_margin(app, accounts, process_id, telemetry, &mut write).await;
     }
 }
 
@@ -290,9 +464,46 @@ pub async fn handle_pending_positions_update(
         .with_quote(&bid_ask.quote);
 
     let positions_to_execute = positions_cache.0.query_and_select_remove(query, |x| {
-        return is_ready_to_execute_pending_position(x, &bid_ask);
+        return !is_pending_group_dormant(&x.base_data)
+            && is_ready_to_execute_pending_position(x, &bid_ask);
     });
 
+    let (positions_to_execute, cancelled_siblings) =
+        resolve_oco_groups(&mut positions_cache, positions_to_execute);
+
+    for cancelled in cancelled_siblings {
+        trade_log::trade_log!(
+            &cancelled.base_data.trader_id,
+            &cancelled.base_data.account_id,
+            process_id,
+            &cancelled.base_data.id,
+            "Cancelled OCO sibling of executed pending order",
+            telemetry.clone(),
+            "position" = &cancelled
+        );
+
+        publish_position_updates(app, &[PositionUpdate::PendingCancelled(&cancelled)]).await;
+
+        app.pending_expirations
+            .lock()
+            .await
+            .disarm(&cancelled.base_data.id);
+
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
+                    process_id: process_id.to_string(),
+                    cancel: Some(map_pending_to_sb_model(cancelled)),
+                    cancel_reason: Some(PendingOrderCancelReason::OcoSiblingExecuted as i32),
+                    execute: None,
+                    create: None,
+                    update: None,
+                }),
+                Some(telemetry),
+            )
+            .await;
+    }
+
     for pending in &positions_to_execute {
         trade_log::trade_log!(
             &pending.base_data.trader_id,
@@ -311,89 +522,24 @@ pub async fn handle_pending_positions_update(
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{collections::HashMap, sync::Arc};
 
     use cfd_engine_sb_contracts::BidAskSbModel;
     use service_sdk::{
-        my_service_bus::abstractions::{
-            publisher::{MessageToPublish, MyServiceBusPublisher},
-            MyServiceBusPublisherClient, PublishError,
-        },
-        my_telemetry::MyTelemetryContext,
-        rust_extensions::AppStates,
+        my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
     };
-    use tokio::sync::RwLock;
-    use trading_sdk::mt_engine::{ActivePositionsCache, MtBidAskCache, PendingPositionsCache};
 
-    use crate::AppContext;
-
-    use super::handle_bid_ask_message;
-
-    pub struct TestPublisherClient {}
-
-    #[async_trait::async_trait]
-    impl MyServiceBusPublisherClient for TestPublisherClient {
-        async fn publish_message(
-            &self,
-            topic_id: &str,
-            message: MessageToPublish,
-            do_retry: bool,
-        ) -> Result<(), PublishError> {
-            todo!();
-        }
+    use crate::{
+        calculate_trigger_prices, set_position_trailing_stop,
+        test_utils::{create_test_active_position, create_test_app, create_test_bid_ask},
+        OutboxMessage, QuoteAgeLimits, TrailingStop,
+    };
 
-        async fn publish_messages(
-            &self,
-            topic_id: &str,
-            message: &[MessageToPublish],
-            do_retry: bool,
-        ) -> Result<(), PublishError> {
-            todo!();
-        }
-    }
+    use super::{handle_active_positions_update_bid_ask, handle_bid_ask_message};
 
     #[tokio::test]
     async fn test_handle_bid_ask() {
-        let app = Arc::new(AppContext {
-            active_positions_cache: Arc::new(RwLock::new(ActivePositionsCache::new())),
-            pending_positions_cache: Arc::new(RwLock::new(PendingPositionsCache::new())),
-            pending_execute_to_confirm_positions: Arc::new(RwLock::new(
-                PendingPositionsCache::new(),
-            )),
-            active_prices_cache: Arc::new(RwLock::new(MtBidAskCache::new())),
-            app_states: Arc::new(AppStates::create_initialized()),
-            active_positions_persistence_publisher: MyServiceBusPublisher::new(
-                "test".to_string(),
-                Arc::new(TestPublisherClient {}),
-                false,
-                service_sdk::my_logger::LOGGER.clone(),
-            ),
-            pending_positions_persistence_publisher: MyServiceBusPublisher::new(
-                "test".to_string(),
-                Arc::new(TestPublisherClient {}),
-                false,
-                service_sdk::my_logger::LOGGER.clone(),
-            ),
-            margin_call_publisher: MyServiceBusPublisher::new(
-                "test".to_string(),
-                Arc::new(TestPublisherClient {}),
-                false,
-                service_sdk::my_logger::LOGGER.clone(),
-            ),
-            topping_up_publisher: MyServiceBusPublisher::new(
-                "test".to_string(),
-                Arc::new(TestPublisherClient {}),
-                false,
-                service_sdk::my_logger::LOGGER.clone(),
-            ),
-            debug: false,
-            pending_need_confirm_publisher: MyServiceBusPublisher::new(
-                "test".to_string(),
-                Arc::new(TestPublisherClient {}),
-                false,
-                service_sdk::my_logger::LOGGER.clone(),
-            ),
-        });
+        let app = create_test_app();
 
         handle_bid_ask_message(
             &app,
@@ -411,4 +557,99 @@ mod tests {
 
         println!("Done");
     }
+
+    #[tokio::test]
+    async fn test_trailing_move_persisted_with_margin_call() {
+        let app = create_test_app();
+
+        let mut position = create_test_active_position("id", "trader", "account");
+        set_position_trailing_stop(
+            &mut position.base_data.metadata,
+            Some(TrailingStop::Distance(0.01)),
+        );
+
+        // The tick is past the margin call price but above the stop out one.
+        let trigger_prices = calculate_trigger_prices(&position);
+        assert!(trigger_prices.margin_call_price.unwrap() > 1.04);
+        assert!(trigger_prices.stop_out_price.unwrap() < 1.04);
+
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        handle_active_positions_update_bid_ask(
+            &app,
+            &create_test_bid_ask(1.04, 1.0402),
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await;
+
+        let mut sl_updates = vec![];
+        while let Some(item) = app.persistence_outbox.peek().await {
+            app.persistence_outbox.ack(item.id).await;
+            if let OutboxMessage::ActivePosition(event) = item.message {
+                if let Some(update) = event.update_position {
+                    sl_updates.push(update.sl_in_instrument_price);
+                }
+            }
+        }
+
+        assert_eq!(sl_updates.len(), 1);
+        assert!((sl_updates[0].unwrap() - 1.03).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_stale_quote_does_not_trigger_stop_loss() {
+        let mut app = create_test_app();
+        Arc::get_mut(&mut app).unwrap().quote_age_limits =
+            QuoteAgeLimits::new(Some(10), &HashMap::new());
+
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.base_data.sl_price = Some(1.09);
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        let mut stale_bid_ask = create_test_bid_ask(1.08, 1.0802);
+        stale_bid_ask.date = DateTimeAsMicroseconds::new(
+            DateTimeAsMicroseconds::now().unix_microseconds - 60_000_000,
+        );
+
+        handle_active_positions_update_bid_ask(
+            &app,
+            &stale_bid_ask,
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await;
+
+        assert!(app
+            .active_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("id")
+            .is_some());
+
+        handle_active_positions_update_bid_ask(
+            &app,
+            &create_test_bid_ask(1.08, 1.0802),
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await;
+
+        assert!(app
+            .active_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("id")
+            .is_none());
+    }
 }
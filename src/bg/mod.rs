@@ -1,5 +1,11 @@
 mod mappers;
 mod bid_ask_subscriber;
+mod outbox_sender;
+mod pending_expiry_timer;
+mod stale_prices_monitor;
 
 pub use mappers::*;
-pub use bid_ask_subscriber::*;
\ No newline at end of file
+pub use bid_ask_subscriber::*;
+pub use outbox_sender::*;
+pub use pending_expiry_timer::*;
+pub use stale_prices_monitor::*;
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{AppContext, OutboxMessage};
+
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+const MAX_BATCH_SIZE: usize = 100;
+
+pub async fn run_outbox_sender(app: Arc<AppContext>) {
+    let mut retry_delay = MIN_RETRY_DELAY;
+
+    loop {
+        let items = app.persistence_outbox.peek_batch(MAX_BATCH_SIZE).await;
+
+        let first = match items.first() {
+            Some(first) => first,
+            None => {
+                app.persistence_outbox.wait_for_message().await;
+                continue;
+            }
+        };
+
+        // Messages go to different topics, so a batch is the leading run of one kind.
+        let (batch_size, publish_result) = match &first.message {
+            OutboxMessage::ActivePosition(_) => {
+                let events: Vec<_> = items
+                    .iter()
+                    .map_while(|x| match &x.message {
+                        OutboxMessage::ActivePosition(event) => Some((event, x.telemetry.as_ref())),
+                        _ => None,
+                    })
+                    .collect();
+
+                (
+                    events.len(),
+                    app.active_positions_persistence_publisher
+                        .publish_messages(events.into_iter())
+                        .await,
+                )
+            }
+            OutboxMessage::PendingPosition(_) => {
+                let events: Vec<_> = items
+                    .iter()
+                    .map_while(|x| match &x.message {
+                        OutboxMessage::PendingPosition(event) => {
+                            Some((event, x.telemetry.as_ref()))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                (
+                    events.len(),
+                    app.pending_positions_persistence_publisher
+                        .publish_messages(events.into_iter())
+                        .await,
+                )
+            }
+            OutboxMessage::ToppingUp(_) => {
+                let events: Vec<_> = items
+                    .iter()
+                    .map_while(|x| match &x.message {
+                        OutboxMessage::ToppingUp(event) => Some((event, x.telemetry.as_ref())),
+                        _ => None,
+                    })
+                    .collect();
+
+                (
+                    events.len(),
+                    app.topping_up_publisher
+                        .publish_messages(events.into_iter())
+                        .await,
+                )
+            }
+        };
+
+        match publish_result {
+            Ok(_) => {
+                for item in &items[..batch_size] {
+                    app.persistence_outbox.ack(item.id).await;
+                }
+
+                retry_delay = MIN_RETRY_DELAY;
+            }
+            Err(err) => {
+                service_sdk::metrics::counter!("outbox_publish_errors").increment(1);
+                println!(
+                    "Outbox publish of {} messages starting with process {} failed: {:?}. Retry in {:?}",
+                    batch_size,
+                    first.message.get_process_id(),
+                    err,
+                    retry_delay
+                );
+
+                tokio::time::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
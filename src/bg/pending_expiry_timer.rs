@@ -0,0 +1,16 @@
+use std::{sync::Arc, time::Duration};
+
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{expire_pending_confirmations, expire_pending_positions, AppContext};
+
+const PENDING_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub async fn run_pending_expiry_timer(app: Arc<AppContext>) {
+    loop {
+        tokio::time::sleep(PENDING_EXPIRY_CHECK_INTERVAL).await;
+        let now = DateTimeAsMicroseconds::now();
+        expire_pending_positions(&app, now).await;
+        expire_pending_confirmations(&app, now).await;
+    }
+}
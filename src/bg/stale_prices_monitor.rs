@@ -0,0 +1,35 @@
+use std::{sync::Arc, time::Duration};
+
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::AppContext;
+
+const STALE_PRICES_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// Feeds are only known once they delivered a quote, a feed that is silent since the start is
+// not reported.
+pub async fn run_stale_prices_monitor(app: Arc<AppContext>) {
+    loop {
+        tokio::time::sleep(STALE_PRICES_CHECK_INTERVAL).await;
+        let now = DateTimeAsMicroseconds::now();
+        let mut stale_count = 0;
+
+        for (asset_pair, date) in app.last_quote_dates.lock().await.iter() {
+            let is_stale = app.quote_age_limits.is_stale(asset_pair, *date, now);
+
+            if is_stale {
+                stale_count += 1;
+                println!(
+                    "Price feed {} is stale, last quote {} sec ago",
+                    asset_pair,
+                    (now.unix_microseconds - date.unix_microseconds) / 1_000_000
+                );
+            }
+
+            service_sdk::metrics::gauge!("price_feed_stale", "asset_pair" => asset_pair.clone())
+                .set(if is_stale { 1.0 } else { 0.0 });
+        }
+
+        service_sdk::metrics::gauge!("stale_price_feeds_count").set(stale_count as f64);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::AccountMarginSummary;
+
+#[derive(Debug, Clone)]
+pub struct AccountBalance {
+    pub balance: f64,
+    pub stop_out_level: Option<f64>,
+    pub last_update_process_id: String,
+}
+
+// Balances are owned by the accounts service and fed in through UpdateAccountBalance. Only
+// accounts with a known balance get a margin summary and take part in the cross-margin stop out.
+pub struct AccountsCache {
+    balances: HashMap<(String, String), AccountBalance>,
+    unconfirmed_profits: HashMap<(String, String), HashMap<String, f64>>,
+    summaries: HashMap<(String, String), AccountMarginSummary>,
+    missing_balances: HashSet<(String, String)>,
+}
+
+impl AccountsCache {
+    pub fn new() -> Self {
+        Self {
+            balances: HashMap::new(),
+            unconfirmed_profits: HashMap::new(),
+            summaries: HashMap::new(),
+            missing_balances: HashSet::new(),
+        }
+    }
+
+    // The balance comes with the close process ids the accounts service has already applied,
+    // their profit is part of it now and is no longer added on top.
+    pub fn set_balance(
+        &mut self,
+        trader_id: &str,
+        account_id: &str,
+        balance: AccountBalance,
+        applied_close_process_ids: &[String],
+    ) {
+        let key = (trader_id.to_string(), account_id.to_string());
+
+        if let Some(unconfirmed) = self.unconfirmed_profits.get_mut(&key) {
+            for process_id in applied_close_process_ids {
+                unconfirmed.remove(process_id);
+            }
+
+            if unconfirmed.is_empty() {
+                self.unconfirmed_profits.remove(&key);
+            }
+        }
+
+        self.missing_balances.remove(&key);
+        self.balances.insert(key, balance);
+    }
+
+    // Balances are not persisted, so after a restart every account trades without the
+    // cross-margin stop out until its balance is fed in again. Returns true the first time an
+    // account with open positions is seen without one.
+    pub fn mark_missing_balance(&mut self, trader_id: &str, account_id: &str) -> bool {
+        return self
+            .missing_balances
+            .insert((trader_id.to_string(), account_id.to_string()));
+    }
+
+    pub fn get_missing_balances_count(&self) -> usize {
+        self.missing_balances.len()
+    }
+
+    pub fn get_balance(&self, trader_id: &str, account_id: &str) -> Option<AccountBalance> {
+        let mut balance = self
+            .balances
+            .get(&(trader_id.to_string(), account_id.to_string()))
+            .cloned()?;
+
+        balance.balance += self.get_unconfirmed_profit(trader_id, account_id);
+
+        return Some(balance);
+    }
+
+    pub fn get_unconfirmed_profit(&self, trader_id: &str, account_id: &str) -> f64 {
+        return self
+            .unconfirmed_profits
+            .get(&(trader_id.to_string(), account_id.to_string()))
+            .map(|x| x.values().sum())
+            .unwrap_or_default();
+    }
+
+    // Positions closed by the engine realize their profit before the accounts service sends
+    // the new balance. The profit is kept by close process id and added to every balance fed
+    // in until one lists the close as applied, so it is neither lost nor counted twice.
+    pub fn apply_realized_profit(
+        &mut self,
+        trader_id: &str,
+        account_id: &str,
+        close_process_id: &str,
+        profit: f64,
+    ) {
+        let key = (trader_id.to_string(), account_id.to_string());
+
+        if !self.balances.contains_key(&key) {
+            return;
+        }
+
+        *self
+            .unconfirmed_profits
+            .entry(key)
+            .or_default()
+            .entry(close_process_id.to_string())
+            .or_default() += profit;
+    }
+
+    pub fn set_summary(&mut self, summary: AccountMarginSummary) {
+        self.summaries.insert(
+            (summary.trader_id.clone(), summary.account_id.clone()),
+            summary,
+        );
+    }
+
+    pub fn get_summary(&self, trader_id: &str, account_id: &str) -> Option<AccountMarginSummary> {
+        return self
+            .summaries
+            .get(&(trader_id.to_string(), account_id.to_string()))
+            .cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountBalance, AccountsCache};
+
+    #[test]
+    fn test_missing_balance_is_reported_once() {
+        let mut cache = AccountsCache::new();
+
+        assert!(cache.mark_missing_balance("trader", "account"));
+        assert!(!cache.mark_missing_balance("trader", "account"));
+        assert_eq!(cache.get_missing_balances_count(), 1);
+
+        cache.set_balance(
+            "trader",
+            "account",
+            AccountBalance {
+                balance: 100.0,
+                stop_out_level: None,
+                last_update_process_id: "balance".to_string(),
+            },
+            &[],
+        );
+
+        assert_eq!(cache.get_missing_balances_count(), 0);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use prost::Message;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    pub trader_id: String,
+    pub process_id: String,
+    pub rpc: &'static str,
+}
+
+impl IdempotencyKey {
+    pub fn new(trader_id: &str, process_id: &str, rpc: &'static str) -> Self {
+        Self {
+            trader_id: trader_id.to_string(),
+            process_id: process_id.to_string(),
+            rpc,
+        }
+    }
+}
+
+pub enum IdempotencyResult<T> {
+    Replay(T),
+    Execute(IdempotencyGuard),
+}
+
+// Holds the per-key lock while the command runs, so a retry that arrives in the middle
+// waits for the original response instead of executing the command a second time.
+pub struct IdempotencyGuard {
+    response: Option<OwnedMutexGuard<Option<Vec<u8>>>>,
+}
+
+impl IdempotencyGuard {
+    pub fn complete<T: Message>(mut self, response: &T) {
+        if let Some(stored) = self.response.as_mut() {
+            **stored = Some(response.encode_to_vec());
+        }
+    }
+}
+
+struct IdempotencyEntries {
+    items: HashMap<IdempotencyKey, Arc<Mutex<Option<Vec<u8>>>>>,
+    expirations: VecDeque<(Instant, IdempotencyKey)>,
+}
+
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<IdempotencyEntries>,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(IdempotencyEntries {
+                items: HashMap::new(),
+                expirations: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub async fn begin<T: Message + Default>(&self, key: IdempotencyKey) -> IdempotencyResult<T> {
+        if key.process_id.is_empty() {
+            return IdempotencyResult::Execute(IdempotencyGuard { response: None });
+        }
+
+        let entry = {
+            let mut entries = self.entries.lock().await;
+            let now = Instant::now();
+
+            while let Some((expires_at, _)) = entries.expirations.front() {
+                if *expires_at > now {
+                    break;
+                }
+
+                let (_, expired_key) = entries.expirations.pop_front().unwrap();
+                entries.items.remove(&expired_key);
+            }
+
+            match entries.items.get(&key) {
+                Some(entry) => entry.clone(),
+                None => {
+                    let entry = Arc::new(Mutex::new(None));
+                    entries.items.insert(key.clone(), entry.clone());
+                    entries.expirations.push_back((now + self.ttl, key));
+                    entry
+                }
+            }
+        };
+
+        let response = entry.lock_owned().await;
+
+        if let Some(stored) = &*response {
+            if let Ok(decoded) = T::decode(stored.as_slice()) {
+                service_sdk::metrics::counter!("idempotent_replays").increment(1);
+                return IdempotencyResult::Replay(decoded);
+            }
+        }
+
+        return IdempotencyResult::Execute(IdempotencyGuard {
+            response: Some(response),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::position_manager_grpc::PositionManagerChargeSwapGrpcResponse;
+
+    use super::{IdempotencyCache, IdempotencyKey, IdempotencyResult};
+
+    #[tokio::test]
+    async fn test_replays_completed_response() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let key = IdempotencyKey::new("trader", "process", "ChargeSwap");
+
+        let guard = match cache
+            .begin::<PositionManagerChargeSwapGrpcResponse>(key.clone())
+            .await
+        {
+            IdempotencyResult::Replay(_) => panic!("First call must be executed"),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        guard.complete(&PositionManagerChargeSwapGrpcResponse {
+            status: 2,
+            position: None,
+        });
+
+        match cache.begin::<PositionManagerChargeSwapGrpcResponse>(key).await {
+            IdempotencyResult::Replay(response) => assert_eq!(response.status, 2),
+            IdempotencyResult::Execute(_) => panic!("Retry must be replayed"),
+        }
+
+        let other_key = IdempotencyKey::new("trader", "process", "OpenPosition");
+        match cache
+            .begin::<PositionManagerChargeSwapGrpcResponse>(other_key)
+            .await
+        {
+            IdempotencyResult::Replay(_) => panic!("Other rpc must be executed"),
+            IdempotencyResult::Execute(_) => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expires_responses() {
+        let cache = IdempotencyCache::new(Duration::from_millis(0));
+        let key = IdempotencyKey::new("trader", "process", "ChargeSwap");
+
+        if let IdempotencyResult::Execute(guard) = cache
+            .begin::<PositionManagerChargeSwapGrpcResponse>(key.clone())
+            .await
+        {
+            guard.complete(&PositionManagerChargeSwapGrpcResponse {
+                status: 0,
+                position: None,
+            });
+        }
+
+        match cache.begin::<PositionManagerChargeSwapGrpcResponse>(key).await {
+            IdempotencyResult::Replay(_) => panic!("Expired response must not be replayed"),
+            IdempotencyResult::Execute(_) => {}
+        }
+    }
+}
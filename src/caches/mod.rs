@@ -0,0 +1,13 @@
+mod idempotency_cache;
+mod pending_expirations_cache;
+mod pending_confirmations_journal;
+mod tick_filter;
+mod accounts_cache;
+mod position_updates_hub;
+
+pub use idempotency_cache::*;
+pub use pending_expirations_cache::*;
+pub use pending_confirmations_journal::*;
+pub use tick_filter::*;
+pub use accounts_cache::*;
+pub use position_updates_hub::*;
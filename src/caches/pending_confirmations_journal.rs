@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+
+// Ids of pending orders handed over for confirmation. The orders themselves stay pending in
+// the persistence until they are executed or cancelled, so the ids are enough to move them
+// back to the awaiting set on restart. The file is rewritten on every change.
+pub struct PendingConfirmationsJournal {
+    path: Option<String>,
+    ids: BTreeSet<String>,
+}
+
+impl PendingConfirmationsJournal {
+    pub fn new(path: Option<&str>) -> Self {
+        let ids = match path {
+            Some(path) => read_journal(path),
+            None => BTreeSet::new(),
+        };
+
+        if !ids.is_empty() {
+            println!(
+                "Restored {} pending orders awaiting confirmation",
+                ids.len()
+            );
+        }
+
+        Self {
+            path: path.map(|x| x.to_string()),
+            ids,
+        }
+    }
+
+    pub fn get_ids(&self) -> Vec<String> {
+        return self.ids.iter().cloned().collect();
+    }
+
+    pub fn add(&mut self, position_id: &str) {
+        if self.ids.insert(position_id.to_string()) {
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, position_id: &str) {
+        if self.ids.remove(position_id) {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let tmp_path = format!("{}.tmp", path);
+        let content: String = self.ids.iter().map(|x| format!("{}\n", x)).collect();
+
+        let result =
+            std::fs::write(&tmp_path, content).and_then(|_| std::fs::rename(&tmp_path, path));
+
+        if let Err(err) = result {
+            service_sdk::metrics::counter!("pending_confirmations_journal_errors").increment(1);
+            println!(
+                "Failed to save pending confirmations journal {}: {:?}",
+                path, err
+            );
+        }
+    }
+}
+
+fn read_journal(path: &str) -> BTreeSet<String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return BTreeSet::new(),
+        Err(err) => panic!(
+            "Can not read pending confirmations journal {}: {:?}",
+            path, err
+        ),
+    };
+
+    return content
+        .lines()
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingConfirmationsJournal;
+
+    #[test]
+    fn test_restores_ids() {
+        let path = std::env::temp_dir().join(format!(
+            "pending-confirmations-{}.journal",
+            uuid::Uuid::new_v4()
+        ));
+        let path = path.to_str().unwrap();
+
+        {
+            let mut journal = PendingConfirmationsJournal::new(Some(path));
+            journal.add("first");
+            journal.add("second");
+            journal.remove("first");
+        }
+
+        let journal = PendingConfirmationsJournal::new(Some(path));
+        assert_eq!(journal.get_ids(), vec!["second".to_string()]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
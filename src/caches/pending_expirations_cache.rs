@@ -0,0 +1,84 @@
+use std::collections::{BTreeSet, HashMap};
+
+use trading_sdk::mt_engine::MtPositionBaseData;
+
+pub const EXPIRE_AT_METADATA_KEY: &str = "ExpireAt";
+
+// Like the other *UnixTimestampMilis fields of the API the expiration is kept in unix
+// microseconds, so it can be compared with DateTimeAsMicroseconds directly.
+pub fn get_pending_expire_at(base_data: &MtPositionBaseData) -> Option<i64> {
+    return base_data
+        .metadata
+        .as_ref()?
+        .get(EXPIRE_AT_METADATA_KEY)?
+        .parse()
+        .ok();
+}
+
+// Index of pending orders by expiration date. It is only a schedule: the pending cache stays
+// the source of truth and an id taken from here may already be executed or cancelled.
+pub struct PendingExpirationsCache {
+    by_date: BTreeSet<(i64, String)>,
+    by_id: HashMap<String, i64>,
+}
+
+impl PendingExpirationsCache {
+    pub fn new() -> Self {
+        Self {
+            by_date: BTreeSet::new(),
+            by_id: HashMap::new(),
+        }
+    }
+
+    pub fn arm(&mut self, position_id: &str, expire_at: i64) {
+        self.disarm(position_id);
+        self.by_date.insert((expire_at, position_id.to_string()));
+        self.by_id.insert(position_id.to_string(), expire_at);
+    }
+
+    pub fn disarm(&mut self, position_id: &str) {
+        if let Some(expire_at) = self.by_id.remove(position_id) {
+            self.by_date.remove(&(expire_at, position_id.to_string()));
+        }
+    }
+
+    pub fn take_expired(&mut self, now: i64) -> Vec<String> {
+        let mut result = vec![];
+
+        while let Some((expire_at, _)) = self.by_date.first() {
+            if *expire_at > now {
+                break;
+            }
+
+            let (_, position_id) = self.by_date.pop_first().unwrap();
+            self.by_id.remove(&position_id);
+            result.push(position_id);
+        }
+
+        return result;
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.by_id.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingExpirationsCache;
+
+    #[test]
+    fn test_take_expired() {
+        let mut cache = PendingExpirationsCache::new();
+        cache.arm("first", 10);
+        cache.arm("second", 20);
+        cache.arm("third", 30);
+        cache.arm("second", 40);
+        cache.disarm("third");
+
+        assert_eq!(cache.take_expired(35), vec!["first".to_string()]);
+        assert_eq!(cache.get_count(), 1);
+        assert_eq!(cache.take_expired(40), vec!["second".to_string()]);
+        assert_eq!(cache.get_count(), 0);
+    }
+}
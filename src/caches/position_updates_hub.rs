@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::position_manager_grpc::{
+    PositionManagerPositionUpdateGrpcModel, PositionManagerPositionUpdateType,
+};
+
+const SUBSCRIBER_BUFFER_SIZE: usize = 1000;
+
+struct PositionUpdatesSubscriber {
+    sender: mpsc::Sender<PositionManagerPositionUpdateGrpcModel>,
+    profit_update_interval: Duration,
+    last_profit_updates: HashMap<String, Instant>,
+}
+
+impl PositionUpdatesSubscriber {
+    fn is_profit_throttled(&mut self, position_id: &str) -> bool {
+        let now = Instant::now();
+
+        if let Some(last) = self.last_profit_updates.get(position_id) {
+            if now.duration_since(*last) < self.profit_update_interval {
+                return true;
+            }
+        }
+
+        self.last_profit_updates
+            .insert(position_id.to_string(), now);
+        return false;
+    }
+}
+
+// Publishing never waits for a subscriber. A full buffer drops profit updates, they are
+// superseded by the next tick anyway. Any other update that does not fit disconnects the
+// subscriber, which then has to subscribe again and gets a fresh snapshot.
+pub struct PositionUpdatesHub {
+    subscribers: HashMap<(String, String), Vec<PositionUpdatesSubscriber>>,
+}
+
+impl PositionUpdatesHub {
+    pub fn new() -> Self {
+        Self {
+            subscribers: HashMap::new(),
+        }
+    }
+
+    pub fn has_subscribers(&self, trader_id: &str, account_id: &str) -> bool {
+        return self
+            .subscribers
+            .get(&(trader_id.to_string(), account_id.to_string()))
+            .map(|x| x.iter().any(|subscriber| !subscriber.sender.is_closed()))
+            .unwrap_or(false);
+    }
+
+    // The snapshot is queued before the subscriber is registered, so it always comes first.
+    pub fn subscribe(
+        &mut self,
+        trader_id: &str,
+        account_id: &str,
+        profit_update_interval: Duration,
+        snapshot: Vec<PositionManagerPositionUpdateGrpcModel>,
+    ) -> mpsc::Receiver<PositionManagerPositionUpdateGrpcModel> {
+        let (sender, receiver) = mpsc::channel(snapshot.len() + SUBSCRIBER_BUFFER_SIZE);
+
+        for update in snapshot {
+            let _ = sender.try_send(update);
+        }
+
+        self.subscribers
+            .entry((trader_id.to_string(), account_id.to_string()))
+            .or_default()
+            .push(PositionUpdatesSubscriber {
+                sender,
+                profit_update_interval,
+                last_profit_updates: HashMap::new(),
+            });
+
+        service_sdk::metrics::gauge!("position_updates_subscribers").increment(1.0);
+
+        return receiver;
+    }
+
+    pub fn publish(
+        &mut self,
+        trader_id: &str,
+        account_id: &str,
+        position_id: &str,
+        update: PositionManagerPositionUpdateGrpcModel,
+    ) {
+        let key = (trader_id.to_string(), account_id.to_string());
+
+        let subscribers = match self.subscribers.get_mut(&key) {
+            Some(subscribers) => subscribers,
+            None => return,
+        };
+
+        let is_profit = update.r#type() == PositionManagerPositionUpdateType::ProfitChanged;
+        let is_closed = update.r#type() == PositionManagerPositionUpdateType::Closed;
+        let count_before = subscribers.len();
+
+        subscribers.retain_mut(|subscriber| {
+            if is_closed {
+                subscriber.last_profit_updates.remove(position_id);
+            }
+
+            if is_profit && subscriber.is_profit_throttled(position_id) {
+                return !subscriber.sender.is_closed();
+            }
+
+            match subscriber.sender.try_send(update.clone()) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) if is_profit => {
+                    service_sdk::metrics::counter!("position_updates_dropped").increment(1);
+                    true
+                }
+                Err(TrySendError::Full(_)) => {
+                    service_sdk::metrics::counter!("position_updates_slow_subscribers")
+                        .increment(1);
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+
+        service_sdk::metrics::gauge!("position_updates_subscribers")
+            .decrement((count_before - subscribers.len()) as f64);
+
+        if subscribers.is_empty() {
+            self.subscribers.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::position_manager_grpc::{
+        PositionManagerPositionUpdateGrpcModel, PositionManagerPositionUpdateType,
+    };
+
+    use super::PositionUpdatesHub;
+
+    fn create_update(
+        update_type: PositionManagerPositionUpdateType,
+    ) -> PositionManagerPositionUpdateGrpcModel {
+        PositionManagerPositionUpdateGrpcModel {
+            r#type: update_type as i32,
+            active_position: None,
+            pending_position: None,
+            closed_position: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profit_updates_are_throttled() {
+        let mut hub = PositionUpdatesHub::new();
+        let mut receiver = hub.subscribe(
+            "trader",
+            "account",
+            Duration::from_secs(60),
+            vec![create_update(PositionManagerPositionUpdateType::Snapshot)],
+        );
+
+        hub.publish(
+            "trader",
+            "account",
+            "id",
+            create_update(PositionManagerPositionUpdateType::ProfitChanged),
+        );
+        hub.publish(
+            "trader",
+            "account",
+            "id",
+            create_update(PositionManagerPositionUpdateType::ProfitChanged),
+        );
+        hub.publish(
+            "trader",
+            "account",
+            "id",
+            create_update(PositionManagerPositionUpdateType::Closed),
+        );
+
+        let received: Vec<_> = [
+            receiver.recv().await.unwrap(),
+            receiver.recv().await.unwrap(),
+            receiver.recv().await.unwrap(),
+        ]
+        .into_iter()
+        .map(|x| x.r#type())
+        .collect();
+
+        assert_eq!(
+            received,
+            vec![
+                PositionManagerPositionUpdateType::Snapshot,
+                PositionManagerPositionUpdateType::ProfitChanged,
+                PositionManagerPositionUpdateType::Closed,
+            ]
+        );
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        hub.publish(
+            "trader",
+            "account",
+            "id",
+            create_update(PositionManagerPositionUpdateType::Closed),
+        );
+        assert!(!hub.has_subscribers("trader", "account"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::{HashMap, VecDeque};
+
+use cfd_engine_sb_contracts::BidAskSbModel;
+use serde::{Deserialize, Serialize};
+
+const QUARANTINE_CAPACITY: usize = 1000;
+// A jump that holds for this many ticks in a row is a new price level, not a spike.
+const SPIKE_CONFIRMATION_TICKS: usize = 3;
+const REJECT_LOG_INTERVAL_MICROS: i64 = 10_000_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TickFilterRules {
+    pub max_jump_percent: Option<f64>,
+    pub min_spread: Option<f64>,
+    pub max_spread: Option<f64>,
+    pub monotonic_time: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TickRejectReason {
+    InvalidPrice,
+    InvertedQuote,
+    SpreadTooNarrow,
+    SpreadTooWide,
+    OutOfOrder,
+    Spike,
+}
+
+impl TickRejectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TickRejectReason::InvalidPrice => "InvalidPrice",
+            TickRejectReason::InvertedQuote => "InvertedQuote",
+            TickRejectReason::SpreadTooNarrow => "SpreadTooNarrow",
+            TickRejectReason::SpreadTooWide => "SpreadTooWide",
+            TickRejectReason::OutOfOrder => "OutOfOrder",
+            TickRejectReason::Spike => "Spike",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuarantinedTick {
+    pub asset_pair: String,
+    pub bid: f64,
+    pub ask: f64,
+    pub date_time_unix_milis: i64,
+    pub reason: TickRejectReason,
+}
+
+struct InstrumentTickState {
+    last_mid: f64,
+    last_date: i64,
+    spike_mid: Option<f64>,
+    spike_confirmations: usize,
+}
+
+// Filters ticks before they reach the caches. Only accepted ticks move the reference price
+// and time of an instrument, rejected ones are kept in a bounded quarantine for inspection.
+pub struct TickFilter {
+    default_rules: TickFilterRules,
+    rules_by_instrument: HashMap<String, TickFilterRules>,
+    states: HashMap<String, InstrumentTickState>,
+    quarantine: VecDeque<QuarantinedTick>,
+    rejected_since_log: usize,
+    last_reject_log: Option<i64>,
+}
+
+impl TickFilter {
+    pub fn new(
+        default_rules: TickFilterRules,
+        rules_by_instrument: HashMap<String, TickFilterRules>,
+    ) -> Self {
+        Self {
+            default_rules,
+            rules_by_instrument,
+            states: HashMap::new(),
+            quarantine: VecDeque::new(),
+            rejected_since_log: 0,
+            last_reject_log: None,
+        }
+    }
+
+    pub fn get_quarantined(&self) -> Vec<QuarantinedTick> {
+        return self.quarantine.iter().cloned().collect();
+    }
+
+    pub fn check(&mut self, bid_ask: &BidAskSbModel) -> Result<(), TickRejectReason> {
+        let result = self.check_tick(bid_ask);
+
+        if let Err(reason) = result {
+            if self.quarantine.len() >= QUARANTINE_CAPACITY {
+                self.quarantine.pop_front();
+            }
+
+            self.quarantine.push_back(QuarantinedTick {
+                asset_pair: bid_ask.id.clone(),
+                bid: bid_ask.bid,
+                ask: bid_ask.ask,
+                date_time_unix_milis: bid_ask.date_time_unix_milis as i64,
+                reason,
+            });
+            self.rejected_since_log += 1;
+        }
+
+        return result;
+    }
+
+    // A bad feed rejects every tick, so rejects are logged as a count at most once per interval.
+    pub fn take_rejects_to_log(&mut self, now_micros: i64) -> Option<usize> {
+        if self.rejected_since_log == 0 {
+            return None;
+        }
+
+        if let Some(last_reject_log) = self.last_reject_log {
+            if now_micros - last_reject_log < REJECT_LOG_INTERVAL_MICROS {
+                return None;
+            }
+        }
+
+        self.last_reject_log = Some(now_micros);
+        let rejected = self.rejected_since_log;
+        self.rejected_since_log = 0;
+        return Some(rejected);
+    }
+
+    fn check_tick(&mut self, bid_ask: &BidAskSbModel) -> Result<(), TickRejectReason> {
+        let rules = self
+            .rules_by_instrument
+            .get(&bid_ask.id)
+            .unwrap_or(&self.default_rules);
+
+        if !bid_ask.bid.is_finite()
+            || !bid_ask.ask.is_finite()
+            || bid_ask.bid <= 0.0
+            || bid_ask.ask <= 0.0
+        {
+            return Err(TickRejectReason::InvalidPrice);
+        }
+
+        let spread = bid_ask.ask - bid_ask.bid;
+
+        if spread < 0.0 {
+            return Err(TickRejectReason::InvertedQuote);
+        }
+
+        if let Some(min_spread) = rules.min_spread {
+            if spread < min_spread {
+                return Err(TickRejectReason::SpreadTooNarrow);
+            }
+        }
+
+        if let Some(max_spread) = rules.max_spread {
+            if spread > max_spread {
+                return Err(TickRejectReason::SpreadTooWide);
+            }
+        }
+
+        let mid = (bid_ask.bid + bid_ask.ask) / 2.0;
+        let date = bid_ask.date_time_unix_milis as i64;
+        let monotonic_time = rules.monotonic_time.unwrap_or(true);
+        let max_jump_percent = rules.max_jump_percent;
+
+        if !self.states.contains_key(&bid_ask.id) {
+            self.states.insert(
+                bid_ask.id.clone(),
+                InstrumentTickState {
+                    last_mid: mid,
+                    last_date: date,
+                    spike_mid: None,
+                    spike_confirmations: 0,
+                },
+            );
+            return Ok(());
+        }
+
+        let state = self.states.get_mut(&bid_ask.id).unwrap();
+
+        if monotonic_time && date < state.last_date {
+            return Err(TickRejectReason::OutOfOrder);
+        }
+
+        if let Some(max_jump_percent) = max_jump_percent {
+            if get_jump_percent(state.last_mid, mid) > max_jump_percent {
+                let confirms_spike = state
+                    .spike_mid
+                    .map(|spike_mid| get_jump_percent(spike_mid, mid) <= max_jump_percent)
+                    .unwrap_or(false);
+
+                state.spike_confirmations = if confirms_spike {
+                    state.spike_confirmations + 1
+                } else {
+                    1
+                };
+                state.spike_mid = Some(mid);
+
+                if state.spike_confirmations < SPIKE_CONFIRMATION_TICKS {
+                    return Err(TickRejectReason::Spike);
+                }
+            }
+        }
+
+        state.last_mid = mid;
+        state.last_date = date;
+        state.spike_mid = None;
+        state.spike_confirmations = 0;
+
+        return Ok(());
+    }
+}
+
+fn get_jump_percent(from: f64, to: f64) -> f64 {
+    return (to - from).abs() / from * 100.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cfd_engine_sb_contracts::BidAskSbModel;
+
+    use super::{TickFilter, TickFilterRules, TickRejectReason};
+
+    fn create_tick(bid: f64, ask: f64, date: i64) -> BidAskSbModel {
+        BidAskSbModel {
+            id: "EURUSD".to_string(),
+            date_time_unix_milis: date as _,
+            bid,
+            ask,
+            base: "EUR".to_string(),
+            quote: "USD".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tick_filter() {
+        let mut filter = TickFilter::new(
+            TickFilterRules {
+                max_jump_percent: Some(5.0),
+                min_spread: None,
+                max_spread: Some(0.01),
+                monotonic_time: None,
+            },
+            HashMap::new(),
+        );
+
+        assert!(filter.check(&create_tick(1.1000, 1.1002, 1)).is_ok());
+        assert_eq!(
+            filter.check(&create_tick(1.1002, 1.1000, 2)),
+            Err(TickRejectReason::InvertedQuote)
+        );
+        assert_eq!(
+            filter.check(&create_tick(0.0, 1.1000, 2)),
+            Err(TickRejectReason::InvalidPrice)
+        );
+        assert_eq!(
+            filter.check(&create_tick(1.1000, 1.2000, 2)),
+            Err(TickRejectReason::SpreadTooWide)
+        );
+        assert_eq!(
+            filter.check(&create_tick(1.1000, 1.1002, 0)),
+            Err(TickRejectReason::OutOfOrder)
+        );
+
+        assert_eq!(
+            filter.check(&create_tick(1.3000, 1.3002, 3)),
+            Err(TickRejectReason::Spike)
+        );
+        assert!(filter.check(&create_tick(1.1001, 1.1003, 4)).is_ok());
+
+        assert_eq!(
+            filter.check(&create_tick(1.3000, 1.3002, 5)),
+            Err(TickRejectReason::Spike)
+        );
+        assert_eq!(
+            filter.check(&create_tick(1.3001, 1.3003, 6)),
+            Err(TickRejectReason::Spike)
+        );
+        assert!(filter.check(&create_tick(1.3002, 1.3004, 7)).is_ok());
+
+        assert_eq!(filter.get_quarantined().len(), 7);
+    }
+
+    #[test]
+    fn test_rejects_log_is_rate_limited() {
+        let mut filter = TickFilter::new(TickFilterRules::default(), HashMap::new());
+
+        assert!(filter.take_rejects_to_log(0).is_none());
+
+        assert!(filter.check(&create_tick(0.0, 1.0, 1)).is_err());
+        assert_eq!(filter.take_rejects_to_log(0), Some(1));
+
+        assert!(filter.check(&create_tick(0.0, 1.0, 2)).is_err());
+        assert!(filter.check(&create_tick(0.0, 1.0, 3)).is_err());
+        assert!(filter.take_rejects_to_log(1_000_000).is_none());
+        assert_eq!(filter.take_rejects_to_log(10_000_000), Some(2));
+        assert_eq!(filter.get_quarantined().len(), 3);
+    }
+}
@@ -0,0 +1,376 @@
+use std::{collections::HashSet, sync::Arc};
+
+use serde::Serialize;
+use service_sdk::my_telemetry::MyTelemetryContext;
+use trading_sdk::{
+    core::EngineCacheQueryBuilder,
+    mt_engine::{
+        ActivePositionsCache, MtPosition, MtPositionActiveState, MtPositionCloseReason,
+        MtPositionClosedState,
+    },
+};
+
+use crate::{close_position_background, AccountBalance, AppContext};
+
+// Balance is the account balance including the margin of open positions, so the equity is
+// the balance plus the floating profit and the charged swaps of the account.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountMarginSummary {
+    pub trader_id: String,
+    pub account_id: String,
+    pub balance: f64,
+    pub equity: f64,
+    pub used_margin: f64,
+    pub free_margin: f64,
+    pub margin_level: Option<f64>,
+    pub positions_count: usize,
+}
+
+pub fn calculate_account_margin(
+    trader_id: &str,
+    account_id: &str,
+    balance: f64,
+    positions: &[&MtPosition<MtPositionActiveState>],
+) -> AccountMarginSummary {
+    let profit: f64 = positions
+        .iter()
+        .map(|x| x.state.profit + x.state.swaps.total)
+        .sum();
+    let used_margin: f64 = positions.iter().map(|x| x.base_data.invest_amount).sum();
+    let equity = balance + profit;
+
+    return AccountMarginSummary {
+        trader_id: trader_id.to_string(),
+        account_id: account_id.to_string(),
+        balance,
+        equity,
+        used_margin,
+        free_margin: equity - used_margin,
+        margin_level: get_margin_level(equity, used_margin),
+        positions_count: positions.len(),
+    };
+}
+
+fn get_margin_level(equity: f64, used_margin: f64) -> Option<f64> {
+    if used_margin <= 0.0 {
+        return None;
+    }
+
+    return Some(equity / used_margin * 100.0);
+}
+
+// Closing a position realizes its profit into the balance, so the equity stays the same and
+// only its margin is released. Positions are closed worst loss first until the margin level
+// is back at the stop out level.
+pub fn select_cross_margin_stop_out(
+    summary: &AccountMarginSummary,
+    positions: &[&MtPosition<MtPositionActiveState>],
+    stop_out_level: f64,
+) -> Vec<String> {
+    match summary.margin_level {
+        Some(margin_level) if margin_level < stop_out_level => {}
+        _ => return vec![],
+    }
+
+    let mut sorted: Vec<_> = positions.iter().collect();
+    sorted.sort_by(|a, b| a.state.profit.total_cmp(&b.state.profit));
+
+    let mut used_margin = summary.used_margin;
+    let mut result = vec![];
+
+    for position in sorted {
+        result.push(position.base_data.id.clone());
+        used_margin -= position.base_data.invest_amount;
+
+        match get_margin_level(summary.equity, used_margin) {
+            Some(margin_level) if margin_level < stop_out_level => {}
+            _ => break,
+        }
+    }
+
+    return result;
+}
+
+// Every close realizes the profit and the charged swaps of the position into the balance.
+pub async fn apply_closed_position_profit(
+    app: &AppContext,
+    closed: &MtPosition<MtPositionClosedState>,
+) {
+    app.accounts_cache.lock().await.apply_realized_profit(
+        &closed.base_data.trader_id,
+        &closed.base_data.account_id,
+        &closed.state.close_process_id,
+        closed.state.active_state.profit + closed.state.active_state.swaps.total,
+    );
+}
+
+pub async fn update_account_balance(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+    balance: AccountBalance,
+    applied_close_process_ids: &[String],
+) -> AccountMarginSummary {
+    let active_cache = app.active_positions_cache.read().await;
+    let query = EngineCacheQueryBuilder::new()
+        .with_client(trader_id)
+        .with_account(account_id);
+    let positions = active_cache.0.query_positions(query);
+
+    let mut accounts_cache = app.accounts_cache.lock().await;
+    accounts_cache.set_balance(
+        trader_id,
+        account_id,
+        balance.clone(),
+        applied_close_process_ids,
+    );
+
+    let summary = calculate_account_margin(
+        trader_id,
+        account_id,
+        balance.balance + accounts_cache.get_unconfirmed_profit(trader_id, account_id),
+        &positions,
+    );
+    accounts_cache.set_summary(summary.clone());
+
+    service_sdk::metrics::gauge!("accounts_without_balance")
+        .set(accounts_cache.get_missing_balances_count() as f64);
+
+    return summary;
+}
+
+pub async fn update_accounts_margin(
+    app: &Arc<AppContext>,
+    accounts: HashSet<(String, String)>,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+    cache: &mut ActivePositionsCache,
+) {
+    for (trader_id, account_id) in accounts {
+        let balance = {
+            let mut accounts_cache = app.accounts_cache.lock().await;
+
+            match accounts_cache.get_balance(&trader_id, &account_id) {
+                Some(balance) => balance,
+                None => {
+                    if accounts_cache.mark_missing_balance(&trader_id, &account_id) {
+                        println!(
+                            "No balance for account {} of trader {}, it has no cross margin stop out until the accounts service sends it",
+                            account_id, trader_id
+                        );
+                        service_sdk::metrics::gauge!("accounts_without_balance")
+                            .set(accounts_cache.get_missing_balances_count() as f64);
+                    }
+
+                    continue;
+                }
+            }
+        };
+
+        let query = EngineCacheQueryBuilder::new()
+            .with_client(&trader_id)
+            .with_account(&account_id);
+
+        let (summary, to_close) = {
+            let positions = cache.0.query_positions(query);
+            let summary =
+                calculate_account_margin(&trader_id, &account_id, balance.balance, &positions);
+            let to_close = balance
+                .stop_out_level
+                .or(app.cross_margin_stop_out_level)
+                .map(|level| select_cross_margin_stop_out(&summary, &positions, level))
+                .unwrap_or_default();
+
+            (summary, to_close)
+        };
+
+        if to_close.is_empty() {
+            app.accounts_cache.lock().await.set_summary(summary);
+            continue;
+        }
+
+        trade_log::trade_log!(
+            &trader_id,
+            &account_id,
+            process_id,
+            "",
+            "Detected cross margin stop out",
+            telemetry.clone(),
+            "summary" = &summary,
+            "positions_to_close" = &to_close
+        );
+
+        for position_id in &to_close {
+            let close_result = close_position_background(
+                app,
+                &trader_id,
+                &account_id,
+                position_id,
+                MtPositionCloseReason::StopOut,
+                process_id,
+                telemetry,
+                cache,
+            )
+            .await;
+
+            trade_log::trade_log!(
+                &trader_id,
+                &account_id,
+                process_id,
+                position_id,
+                "Closed position by cross margin stop out",
+                telemetry.clone(),
+                "close_result" = &close_result
+            );
+        }
+
+        let query = EngineCacheQueryBuilder::new()
+            .with_client(&trader_id)
+            .with_account(&account_id);
+        let positions = cache.0.query_positions(query);
+        let mut accounts_cache = app.accounts_cache.lock().await;
+
+        if let Some(balance) = accounts_cache.get_balance(&trader_id, &account_id) {
+            accounts_cache.set_summary(calculate_account_margin(
+                &trader_id,
+                &account_id,
+                balance.balance,
+                &positions,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::MtPositionCloseReason;
+
+    use crate::{
+        close_position,
+        test_utils::{create_test_active_position, create_test_app},
+        AccountBalance,
+    };
+
+    use super::{calculate_account_margin, select_cross_margin_stop_out, update_account_balance};
+
+    #[test]
+    fn test_cross_margin_stop_out_closes_worst_first() {
+        let mut first = create_test_active_position("first", "trader", "account");
+        first.state.profit = -60.0;
+        let mut second = create_test_active_position("second", "trader", "account");
+        second.state.profit = -90.0;
+        let mut third = create_test_active_position("third", "trader", "account");
+        third.state.profit = 10.0;
+
+        let positions = vec![&first, &second, &third];
+        let summary = calculate_account_margin("trader", "account", 300.0, &positions);
+
+        assert_eq!(summary.equity, 160.0);
+        assert_eq!(summary.used_margin, 300.0);
+
+        let to_close = select_cross_margin_stop_out(&summary, &positions, 85.0);
+        assert_eq!(to_close, vec!["second".to_string(), "first".to_string()]);
+
+        assert!(select_cross_margin_stop_out(&summary, &positions, 50.0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_realizes_profit_and_swaps() {
+        let app = create_test_app();
+
+        app.accounts_cache.lock().await.set_balance(
+            "trader",
+            "account",
+            AccountBalance {
+                balance: 1000.0,
+                stop_out_level: None,
+                last_update_process_id: "balance".to_string(),
+            },
+            &[],
+        );
+
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.state.profit = 25.0;
+        position.state.swaps.total = -5.0;
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+        close_position(
+            &app,
+            "trader",
+            "account",
+            "id",
+            MtPositionCloseReason::ClientCommand,
+            "process",
+            &telemetry,
+        )
+        .await
+        .unwrap();
+
+        let balance = app
+            .accounts_cache
+            .lock()
+            .await
+            .get_balance("trader", "account")
+            .unwrap();
+        assert_eq!(balance.balance, 1020.0);
+    }
+
+    #[tokio::test]
+    async fn test_realized_profit_is_kept_until_balance_confirms_it() {
+        let app = create_test_app();
+        let balance = |balance: f64| AccountBalance {
+            balance,
+            stop_out_level: None,
+            last_update_process_id: "balance".to_string(),
+        };
+
+        update_account_balance(&app, "trader", "account", balance(1000.0), &[]).await;
+
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.state.profit = 20.0;
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        close_position(
+            &app,
+            "trader",
+            "account",
+            "id",
+            MtPositionCloseReason::ClientCommand,
+            "close",
+            &service_sdk::my_telemetry::MyTelemetryContext::new(),
+        )
+        .await
+        .unwrap();
+
+        let summary = update_account_balance(&app, "trader", "account", balance(1000.0), &[]).await;
+        assert_eq!(summary.balance, 1020.0);
+
+        let summary = update_account_balance(
+            &app,
+            "trader",
+            "account",
+            balance(1020.0),
+            &["close".to_string()],
+        )
+        .await;
+        assert_eq!(summary.balance, 1020.0);
+        assert_eq!(
+            app.accounts_cache
+                .lock()
+                .await
+                .get_balance("trader", "account")
+                .unwrap()
+                .balance,
+            1020.0
+        );
+    }
+}
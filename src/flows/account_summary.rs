@@ -0,0 +1,157 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::Serialize;
+use trading_sdk::{
+    core::EngineCacheQueryBuilder,
+    mt_engine::{
+        MtPosition, MtPositionActiveState, MtPositionBaseData, MtPositionPendingState,
+        MtPositionSide,
+    },
+};
+
+use crate::{calculate_account_margin, AccountMarginSummary, AppContext};
+
+// Exposure is the signed volume (invest amount * leverage), buys are positive and sells are
+// negative, so hedged positions of an instrument cancel out.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstrumentExposure {
+    pub asset_pair: String,
+    pub net_exposure: f64,
+    pub pending_exposure: f64,
+    pub active_positions_count: usize,
+    pub pending_positions_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountSummary {
+    pub trader_id: String,
+    pub account_id: String,
+    pub profit: f64,
+    pub invest_amount: f64,
+    pub reserved_topping_up: f64,
+    pub swaps: f64,
+    pub active_positions_count: usize,
+    pub pending_positions_count: usize,
+    pub pending_invest_amount: f64,
+    pub exposures: Vec<InstrumentExposure>,
+    pub margin: Option<AccountMarginSummary>,
+}
+
+fn get_signed_volume(base_data: &MtPositionBaseData) -> f64 {
+    let volume = base_data.invest_amount * base_data.leverage;
+
+    match base_data.side {
+        MtPositionSide::Buy => volume,
+        MtPositionSide::Sell => -volume,
+    }
+}
+
+pub fn calculate_account_summary(
+    trader_id: &str,
+    account_id: &str,
+    active: &[&MtPosition<MtPositionActiveState>],
+    pending: &[&MtPosition<MtPositionPendingState>],
+) -> AccountSummary {
+    let mut exposures: BTreeMap<String, InstrumentExposure> = BTreeMap::new();
+
+    for position in active {
+        let exposure = exposures
+            .entry(position.base_data.asset_pair.clone())
+            .or_default();
+        exposure.net_exposure += get_signed_volume(&position.base_data);
+        exposure.active_positions_count += 1;
+    }
+
+    for position in pending {
+        let exposure = exposures
+            .entry(position.base_data.asset_pair.clone())
+            .or_default();
+        exposure.pending_exposure += get_signed_volume(&position.base_data);
+        exposure.pending_positions_count += 1;
+    }
+
+    return AccountSummary {
+        trader_id: trader_id.to_string(),
+        account_id: account_id.to_string(),
+        profit: active.iter().map(|x| x.state.profit).sum(),
+        invest_amount: active.iter().map(|x| x.base_data.invest_amount).sum(),
+        reserved_topping_up: active.iter().filter_map(|x| x.state.topping_up).sum(),
+        swaps: active.iter().map(|x| x.state.swaps.total).sum(),
+        active_positions_count: active.len(),
+        pending_positions_count: pending.len(),
+        pending_invest_amount: pending.iter().map(|x| x.base_data.invest_amount).sum(),
+        exposures: exposures
+            .into_iter()
+            .map(|(asset_pair, mut exposure)| {
+                exposure.asset_pair = asset_pair;
+                return exposure;
+            })
+            .collect(),
+        margin: None,
+    };
+}
+
+// Margin figures are only known for accounts the balance was fed in for.
+pub async fn get_account_summary(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+) -> AccountSummary {
+    let active_cache = app.active_positions_cache.read().await;
+    let pending_cache = app.pending_positions_cache.read().await;
+
+    let active = active_cache.0.query_positions(
+        EngineCacheQueryBuilder::new()
+            .with_client(trader_id)
+            .with_account(account_id),
+    );
+    let pending = pending_cache.0.query_positions(
+        EngineCacheQueryBuilder::new()
+            .with_client(trader_id)
+            .with_account(account_id),
+    );
+
+    let mut summary = calculate_account_summary(trader_id, account_id, &active, &pending);
+
+    summary.margin = app
+        .accounts_cache
+        .lock()
+        .await
+        .get_balance(trader_id, account_id)
+        .map(|balance| calculate_account_margin(trader_id, account_id, balance.balance, &active));
+
+    return summary;
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::MtPositionSide;
+
+    use crate::test_utils::{create_test_active_position, create_test_pending_position};
+
+    use super::calculate_account_summary;
+
+    #[test]
+    fn test_account_summary_nets_exposure() {
+        let mut buy = create_test_active_position("buy", "trader", "account");
+        buy.state.profit = 15.0;
+        buy.state.swaps.total = -3.0;
+        let mut sell = create_test_active_position("sell", "trader", "account");
+        sell.base_data.side = MtPositionSide::Sell;
+        sell.base_data.invest_amount = 40.0;
+        sell.state.profit = -5.0;
+        let pending = create_test_pending_position("pending", "trader", "account");
+
+        let summary = calculate_account_summary("trader", "account", &[&buy, &sell], &[&pending]);
+
+        assert_eq!(summary.profit, 10.0);
+        assert_eq!(summary.invest_amount, 140.0);
+        assert_eq!(summary.swaps, -3.0);
+        assert_eq!(summary.active_positions_count, 2);
+        assert_eq!(summary.pending_positions_count, 1);
+        assert_eq!(summary.exposures.len(), 1);
+        assert_eq!(summary.exposures[0].asset_pair, "EURUSD");
+        assert_eq!(summary.exposures[0].net_exposure, 600.0);
+        assert_eq!(summary.exposures[0].pending_exposure, 1000.0);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+use trading_sdk::mt_engine::{MtPosition, MtPositionActiveState, MtPositionSide};
+
+use crate::utils::is_sl_improved;
+
+pub const BREAK_EVEN_PROFIT_METADATA_KEY: &str = "BreakEvenProfit";
+pub const BREAK_EVEN_PERCENT_METADATA_KEY: &str = "BreakEvenPercent";
+pub const BREAK_EVEN_OFFSET_METADATA_KEY: &str = "BreakEvenOffset";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakEvenTrigger {
+    Profit(f64),
+    Percent(f64),
+}
+
+// Like the trailing stop, the break-even settings are kept in the position metadata. The
+// offset is in asset price and is applied in the profitable direction of the position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakEvenStop {
+    pub trigger: BreakEvenTrigger,
+    pub offset: f64,
+}
+
+impl BreakEvenStop {
+    pub fn from_request(
+        profit: Option<f64>,
+        percent: Option<f64>,
+        offset: Option<f64>,
+    ) -> Option<Self> {
+        let trigger = match (profit, percent) {
+            (Some(profit), _) => BreakEvenTrigger::Profit(profit),
+            (None, Some(percent)) => BreakEvenTrigger::Percent(percent),
+            (None, None) => return None,
+        };
+
+        return Some(Self {
+            trigger,
+            offset: offset.unwrap_or(0.0),
+        });
+    }
+
+    pub fn from_metadata(metadata: Option<&HashMap<String, String>>) -> Option<Self> {
+        let metadata = metadata?;
+        let get_value = |key: &str| metadata.get(key).and_then(|x| x.parse::<f64>().ok());
+
+        return Self::from_request(
+            get_value(BREAK_EVEN_PROFIT_METADATA_KEY),
+            get_value(BREAK_EVEN_PERCENT_METADATA_KEY),
+            get_value(BREAK_EVEN_OFFSET_METADATA_KEY),
+        );
+    }
+
+    pub fn write_metadata(break_even: Option<Self>, metadata: &mut HashMap<String, String>) {
+        metadata.remove(BREAK_EVEN_PROFIT_METADATA_KEY);
+        metadata.remove(BREAK_EVEN_PERCENT_METADATA_KEY);
+        metadata.remove(BREAK_EVEN_OFFSET_METADATA_KEY);
+
+        let break_even = match break_even {
+            Some(break_even) => break_even,
+            None => return,
+        };
+
+        match break_even.trigger {
+            BreakEvenTrigger::Profit(profit) => {
+                metadata.insert(
+                    BREAK_EVEN_PROFIT_METADATA_KEY.to_string(),
+                    profit.to_string(),
+                );
+            }
+            BreakEvenTrigger::Percent(percent) => {
+                metadata.insert(
+                    BREAK_EVEN_PERCENT_METADATA_KEY.to_string(),
+                    percent.to_string(),
+                );
+            }
+        }
+
+        metadata.insert(
+            BREAK_EVEN_OFFSET_METADATA_KEY.to_string(),
+            break_even.offset.to_string(),
+        );
+    }
+
+    pub fn get_profit_threshold(&self, invest_amount: f64) -> f64 {
+        match self.trigger {
+            BreakEvenTrigger::Profit(profit) => profit,
+            BreakEvenTrigger::Percent(percent) => invest_amount * percent / 100.0,
+        }
+    }
+}
+
+pub fn set_position_break_even_stop(
+    metadata: &mut Option<HashMap<String, String>>,
+    break_even: Option<BreakEvenStop>,
+) {
+    if break_even.is_none() && metadata.is_none() {
+        return;
+    }
+
+    BreakEvenStop::write_metadata(break_even, metadata.get_or_insert_with(HashMap::new));
+}
+
+// Moves sl_price to the open price plus offset once the profit reaches the threshold. The stop
+// is never moved back, so once it is at break-even (or further) this is a no-op.
+pub fn update_break_even_stop(
+    position: &mut MtPosition<MtPositionActiveState>,
+    process_id: &str,
+) -> bool {
+    let break_even = match BreakEvenStop::from_metadata(position.base_data.metadata.as_ref()) {
+        Some(break_even) => break_even,
+        None => return false,
+    };
+
+    if position.state.profit < break_even.get_profit_threshold(position.base_data.invest_amount) {
+        return false;
+    }
+
+    let open_price = position.state.open_data.asset_open_price;
+
+    let sl_price = match position.base_data.side {
+        MtPositionSide::Buy => open_price + break_even.offset,
+        MtPositionSide::Sell => open_price - break_even.offset,
+    };
+
+    if !is_sl_improved(
+        &position.base_data.side,
+        sl_price,
+        position.base_data.sl_price,
+    ) {
+        return false;
+    }
+
+    // With a large offset the profit threshold can be reached before the price has passed the
+    // break-even level. Such a stop would close the position right away, so it waits for the
+    // price instead.
+    let close_price = position.state.asset_active_price;
+    let is_through_market = match position.base_data.side {
+        MtPositionSide::Buy => sl_price >= close_price,
+        MtPositionSide::Sell => sl_price <= close_price,
+    };
+
+    if is_through_market {
+        return false;
+    }
+
+    position.base_data.sl_price = Some(sl_price);
+    position.base_data.last_update_date = DateTimeAsMicroseconds::now();
+    position.base_data.last_update_process_id = process_id.to_string();
+
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::create_test_active_position;
+
+    use super::{set_position_break_even_stop, update_break_even_stop, BreakEvenStop};
+
+    #[test]
+    fn test_break_even_moves_stop_after_threshold() {
+        let mut position = create_test_active_position("id", "trader", "account");
+        set_position_break_even_stop(
+            &mut position.base_data.metadata,
+            BreakEvenStop::from_request(None, Some(10.0), Some(0.001)),
+        );
+
+        position.state.asset_active_price = 1.11;
+
+        position.state.profit = 5.0;
+        assert!(!update_break_even_stop(&mut position, "process"));
+        assert_eq!(position.base_data.sl_price, None);
+
+        position.state.profit = 10.0;
+        assert!(update_break_even_stop(&mut position, "process"));
+        assert!((position.base_data.sl_price.unwrap() - 1.101).abs() < 1e-12);
+
+        position.state.profit = 20.0;
+        assert!(!update_break_even_stop(&mut position, "process"));
+    }
+
+    #[test]
+    fn test_break_even_does_not_set_stop_through_market() {
+        let mut position = create_test_active_position("id", "trader", "account");
+        set_position_break_even_stop(
+            &mut position.base_data.metadata,
+            BreakEvenStop::from_request(Some(10.0), None, Some(0.01)),
+        );
+
+        position.state.profit = 10.0;
+        position.state.asset_active_price = 1.105;
+        assert!(!update_break_even_stop(&mut position, "process"));
+        assert_eq!(position.base_data.sl_price, None);
+
+        position.state.asset_active_price = 1.12;
+        assert!(update_break_even_stop(&mut position, "process"));
+        assert!((position.base_data.sl_price.unwrap() - 1.11).abs() < 1e-12);
+    }
+}
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
-use cfd_engine_sb_contracts::PendingPositionPersistenceEvent;
+use cfd_engine_sb_contracts::{PendingOrderCancelReason, PendingPositionPersistenceEvent};
 use service_sdk::my_telemetry::MyTelemetryContext;
 use trading_sdk::mt_engine::{MtEngineError, MtPosition, MtPositionPendingState};
 
 use crate::{
     map_pending_to_sb_model, position_manager_grpc::PositionManagerCancelPendingGrpcRequest,
-    AppContext,
+    publish_position_updates, take_bracket_exits, AppContext, OutboxMessage, PositionUpdate,
 };
 
 pub async fn cancel_pending(
@@ -14,23 +14,83 @@ pub async fn cancel_pending(
     request: PositionManagerCancelPendingGrpcRequest,
     telemetry: &MyTelemetryContext,
 ) -> Result<MtPosition<MtPositionPendingState>, MtEngineError> {
-    let removed = {
+    let (removed, exits) = {
         let mut write = app.pending_positions_cache.write().await;
-        write.0.remove_position(&request.id)
-    }
-    .ok_or(MtEngineError::PositionNotFound)?;
+        let removed = write
+            .0
+            .remove_position(&request.id)
+            .ok_or(MtEngineError::PositionNotFound)?;
+        let exits = take_bracket_exits(&mut write, &removed.base_data);
+        (removed, exits)
+    };
+
+    app.pending_expirations
+        .lock()
+        .await
+        .disarm(&removed.base_data.id);
+
+    let process_id = uuid::Uuid::new_v4().to_string();
 
     let sb_event = PendingPositionPersistenceEvent {
-        process_id: uuid::Uuid::new_v4().to_string(),
+        process_id: process_id.clone(),
         cancel: Some(map_pending_to_sb_model(removed.clone())),
+        cancel_reason: Some(PendingOrderCancelReason::ClientCommand as i32),
         execute: None,
         create: None,
+        update: None,
     };
 
-    app.pending_positions_persistence_publisher
-        .publish(&sb_event, Some(telemetry))
-        .await
-        .unwrap();
+    app.persistence_outbox
+        .enqueue(OutboxMessage::PendingPosition(sb_event), Some(telemetry))
+        .await;
+
+    publish_position_updates(app, &[PositionUpdate::PendingCancelled(&removed)]).await;
+
+    cancel_bracket_exits(app, exits, &process_id, telemetry).await;
 
     return Ok(removed);
 }
+
+// The exit legs taken from the cache together with their entry are persisted as cancelled
+// orders.
+pub async fn cancel_bracket_exits(
+    app: &AppContext,
+    exits: Vec<MtPosition<MtPositionPendingState>>,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) {
+    if exits.is_empty() {
+        return;
+    }
+
+    let mut expirations = app.pending_expirations.lock().await;
+
+    for exit in exits {
+        expirations.disarm(&exit.base_data.id);
+        publish_position_updates(app, &[PositionUpdate::PendingCancelled(&exit)]).await;
+
+        trade_log::trade_log!(
+            &exit.base_data.trader_id,
+            &exit.base_data.account_id,
+            process_id,
+            &exit.base_data.id,
+            "Cancelled bracket exit leg together with its entry",
+            telemetry.clone(),
+            "position" = &exit
+        );
+
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
+                    process_id: process_id.to_string(),
+                    cancel: Some(map_pending_to_sb_model(exit)),
+                    cancel_reason: Some(PendingOrderCancelReason::ParentCancelled as i32),
+                    execute: None,
+                    create: None,
+                    update: None,
+                }),
+                Some(telemetry),
+            )
+            .await;
+    }
+}
@@ -2,7 +2,9 @@ use cfd_engine_sb_contracts::PositionPersistenceEvent;
 use service_sdk::{my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds};
 use trading_sdk::mt_engine::{MtPosition, MtPositionActiveState};
 
-use crate::{map_active_to_sb_model, AppContext};
+use crate::{
+    map_active_to_sb_model, publish_position_updates, AppContext, OutboxMessage, PositionUpdate,
+};
 
 pub async fn charge_swaps(
     app: &AppContext,
@@ -25,18 +27,19 @@ pub async fn charge_swaps(
     });
 
     if let Some(updated_position) = updated_position {
-        app.active_positions_persistence_publisher
-            .publish(
-                &PositionPersistenceEvent {
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
                     process_id: process_id.to_string(),
                     update_position: Some(map_active_to_sb_model(updated_position.clone())),
                     close_position: None,
                     create_position: None,
-                },
+                }),
                 Some(telemetry),
             )
-            .await
-            .unwrap();
+            .await;
+
+        publish_position_updates(app, &[PositionUpdate::Updated(&updated_position)]).await;
 
         return Some(updated_position);
     }
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use serde::Serialize;
+use service_sdk::my_telemetry::MyTelemetryContext;
+use trading_sdk::mt_engine::{
+    convert_position_to_closed, update_position_pl, MtPosition, MtPositionActiveState,
+    MtPositionCloseReason, MtPositionClosedState,
+};
+
+use crate::{
+    apply_closed_position_profit, enqueue_split_part_create, map_active_to_sb_model,
+    map_closed_to_sb, publish_position_updates, split_active_position, utils::is_same_side,
+    AppContext, EngineError, OutboxMessage, PositionUpdate,
+};
+
+pub const CLOSE_BY_POSITION_ID_METADATA_KEY: &str = "CloseByPositionId";
+
+const CLOSE_BY_AMOUNT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Serialize)]
+pub struct CloseByResult {
+    pub closed_positions: Vec<MtPosition<MtPositionClosedState>>,
+    pub remaining_position: Option<MtPosition<MtPositionActiveState>>,
+}
+
+// Both legs are closed at the open price of the opposite position: the requested position
+// takes the whole offset P&L and the overlapping part of the opposite one closes flat, so no
+// spread is paid. Volumes are compared as invest amount * leverage.
+pub async fn close_by(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+    position_id: &str,
+    opposite_position_id: &str,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) -> Result<CloseByResult, EngineError> {
+    if position_id == opposite_position_id {
+        return Err(EngineError::CloseByMismatch);
+    }
+
+    let mut cache = app.active_positions_cache.write().await;
+
+    let position = cache
+        .0
+        .get_by_id(position_id)
+        .ok_or(EngineError::PositionNotFound)?;
+    let opposite = cache
+        .0
+        .get_by_id(opposite_position_id)
+        .ok_or(EngineError::PositionNotFound)?;
+
+    if position.base_data.asset_pair != opposite.base_data.asset_pair
+        || is_same_side(&position.base_data.side, &opposite.base_data.side)
+    {
+        return Err(EngineError::CloseByMismatch);
+    }
+
+    let close_price = opposite.state.open_data.asset_open_price;
+    let volume = f64::min(
+        position.base_data.invest_amount * position.base_data.leverage,
+        opposite.base_data.invest_amount * opposite.base_data.leverage,
+    );
+
+    let position = cache.0.remove_position(position_id).unwrap();
+    let opposite = cache.0.remove_position(opposite_position_id).unwrap();
+
+    let mut closed_positions = vec![];
+    let mut split_parts = vec![];
+    let mut remaining_position = None;
+
+    for (leg, offset_by) in [(position, opposite_position_id), (opposite, position_id)] {
+        let close_amount = volume / leg.base_data.leverage;
+
+        let leg = if close_amount < leg.base_data.invest_amount - CLOSE_BY_AMOUNT_TOLERANCE {
+            let (closed_part, remaining) = split_active_position(leg, close_amount, process_id);
+            cache.0.add_position(remaining.clone());
+            remaining_position = Some(remaining);
+            split_parts.push(closed_part.clone());
+            closed_part
+        } else {
+            leg
+        };
+
+        closed_positions.push(close_leg_at_price(leg, close_price, offset_by, process_id));
+    }
+
+    trade_log::trade_log!(
+        trader_id,
+        account_id,
+        process_id,
+        position_id,
+        "Executing close by",
+        telemetry.clone(),
+        "opposite_position_id" = &opposite_position_id,
+        "closed_positions" = &closed_positions,
+        "remaining_position" = &remaining_position
+    );
+
+    for split_part in &split_parts {
+        enqueue_split_part_create(app, process_id, split_part, telemetry).await;
+    }
+
+    for closed in &closed_positions {
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                    process_id: process_id.to_string(),
+                    update_position: None,
+                    close_position: Some(map_closed_to_sb(closed)),
+                    create_position: None,
+                }),
+                Some(telemetry),
+            )
+            .await;
+
+        apply_closed_position_profit(app, closed).await;
+    }
+
+    if let Some(remaining) = &remaining_position {
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                    process_id: process_id.to_string(),
+                    update_position: Some(map_active_to_sb_model(remaining.clone())),
+                    close_position: None,
+                    create_position: None,
+                }),
+                Some(telemetry),
+            )
+            .await;
+    }
+
+    let mut updates: Vec<_> = closed_positions
+        .iter()
+        .map(PositionUpdate::Closed)
+        .collect();
+    updates.extend(remaining_position.iter().map(PositionUpdate::Updated));
+    publish_position_updates(app, &updates).await;
+
+    return Ok(CloseByResult {
+        closed_positions,
+        remaining_position,
+    });
+}
+
+fn close_leg_at_price(
+    mut position: MtPosition<MtPositionActiveState>,
+    close_price: f64,
+    offset_by: &str,
+    process_id: &str,
+) -> MtPosition<MtPositionClosedState> {
+    position.state.asset_active_price = close_price;
+    position.state.asset_active_bid_ask.bid = close_price;
+    position.state.asset_active_bid_ask.ask = close_price;
+    update_position_pl(&mut position);
+
+    // The close reason tells it was a close by, the metadata keeps the position it was offset by.
+    position
+        .base_data
+        .metadata
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            CLOSE_BY_POSITION_ID_METADATA_KEY.to_string(),
+            offset_by.to_string(),
+        );
+
+    let mut closed = convert_position_to_closed(
+        position,
+        MtPositionCloseReason::CloseBy,
+        process_id.to_string(),
+    );
+    closed.state.asset_close_price = close_price;
+
+    return closed;
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::{MtPositionCloseReason, MtPositionSide};
+
+    use crate::{
+        test_utils::{create_test_active_position, create_test_app},
+        EngineError,
+    };
+
+    use super::close_by;
+
+    #[tokio::test]
+    async fn test_close_by_offsets_overlapping_volume() {
+        let app = create_test_app();
+
+        let buy = create_test_active_position("buy", "trader", "account");
+
+        let mut sell = create_test_active_position("sell", "trader", "account");
+        sell.base_data.side = MtPositionSide::Sell;
+        sell.base_data.invest_amount = 40.0;
+        sell.state.open_data.asset_open_price = 1.2;
+
+        {
+            let mut cache = app.active_positions_cache.write().await;
+            cache.0.add_position(buy);
+            cache.0.add_position(sell);
+        }
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+
+        let result = close_by(
+            &app, "trader", "account", "buy", "sell", "process", &telemetry,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.closed_positions.len(), 2);
+        for closed in &result.closed_positions {
+            assert!(matches!(
+                closed.state.close_reason,
+                MtPositionCloseReason::CloseBy
+            ));
+            assert_eq!(closed.state.asset_close_price, 1.2);
+            assert_eq!(closed.base_data.invest_amount, 40.0);
+        }
+
+        let remaining = result.remaining_position.unwrap();
+        assert_eq!(remaining.base_data.id, "buy");
+        assert_eq!(remaining.base_data.invest_amount, 60.0);
+
+        let cache = app.active_positions_cache.read().await;
+        assert!(cache.0.get_by_id("buy").is_some());
+        assert!(cache.0.get_by_id("sell").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_close_by_rejects_same_side() {
+        let app = create_test_app();
+
+        {
+            let mut cache = app.active_positions_cache.write().await;
+            cache
+                .0
+                .add_position(create_test_active_position("first", "trader", "account"));
+            cache
+                .0
+                .add_position(create_test_active_position("second", "trader", "account"));
+        }
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+
+        let result = close_by(
+            &app, "trader", "account", "first", "second", "process", &telemetry,
+        )
+        .await;
+
+        assert!(matches!(result, Err(EngineError::CloseByMismatch)));
+        assert!(app
+            .active_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("first")
+            .is_some());
+    }
+}
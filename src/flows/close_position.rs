@@ -7,7 +7,10 @@ use trading_sdk::mt_engine::{
     MtPositionClosedState,
 };
 
-use crate::{map_closed_to_sb, AppContext, EngineError};
+use crate::{
+    apply_closed_position_profit, map_closed_to_sb, publish_position_updates, AppContext,
+    EngineError, OutboxMessage, PositionUpdate,
+};
 
 pub async fn close_position(
     app: &Arc<AppContext>,
@@ -51,10 +54,13 @@ pub async fn close_position(
         create_position: None,
     };
 
-    app.active_positions_persistence_publisher
-        .publish(&sb_event, Some(telemetry))
-        .await
-        .unwrap();
+    app.persistence_outbox
+        .enqueue(OutboxMessage::ActivePosition(sb_event), Some(telemetry))
+        .await;
+
+    apply_closed_position_profit(app, &closed).await;
+
+    publish_position_updates(app, &[PositionUpdate::Closed(&closed)]).await;
 
     return Ok(closed);
 }
@@ -100,10 +106,13 @@ pub async fn close_position_background(
         create_position: None,
     };
 
-    app.active_positions_persistence_publisher
-        .publish(&sb_event, Some(telemetry))
-        .await
-        .unwrap();
+    app.persistence_outbox
+        .enqueue(OutboxMessage::ActivePosition(sb_event), Some(telemetry))
+        .await;
+
+    apply_closed_position_profit(app, &closed).await;
+
+    publish_position_updates(app, &[PositionUpdate::Closed(&closed)]).await;
 
     return Ok(closed);
 }
@@ -1,70 +1,188 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use cfd_engine_sb_contracts::{
-    PendingOrderNeedApproveEvent, PendingPositionPersistenceEvent, PositionPersistenceEvent,
+    PendingOrderCancelReason, PendingOrderNeedApproveEvent, PendingPositionPersistenceEvent,
+    PositionPersistenceEvent,
+};
+use serde::Serialize;
+use service_sdk::{
+    my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
 };
 use trading_sdk::mt_engine::{
-    execute_pending_position, MtEngineError, MtPosition, MtPositionActiveState,
-    MtPositionPendingState,
+    execute_pending_position, get_close_price, get_pending_position_type, MtPosition,
+    MtPositionActiveState, MtPositionPendingState,
 };
 use uuid::Uuid;
 
-use crate::AppContext;
+use crate::{
+    apply_bracket_exits, cancel_bracket_exits, get_pending_expire_at,
+    position_manager_grpc::PositionManagerRejectPendingExecutionAction, publish_position_updates,
+    take_bracket_exits, AppContext, EngineError, OutboxMessage, PositionUpdate,
+};
+
+pub const PENDING_CONFIRMATION_TIMEOUT_PROCESS_ID_PREFIX: &str = "pending-confirmation-timeout.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RejectPendingAction {
+    ReturnToPending,
+    Cancel,
+}
+
+impl RejectPendingAction {
+    pub fn parse(src: &str) -> Option<Self> {
+        match src {
+            "ReturnToPending" => Some(RejectPendingAction::ReturnToPending),
+            "Cancel" => Some(RejectPendingAction::Cancel),
+            _ => None,
+        }
+    }
+}
+
+impl From<PositionManagerRejectPendingExecutionAction> for RejectPendingAction {
+    fn from(src: PositionManagerRejectPendingExecutionAction) -> Self {
+        match src {
+            PositionManagerRejectPendingExecutionAction::ReturnToPending => {
+                RejectPendingAction::ReturnToPending
+            }
+            PositionManagerRejectPendingExecutionAction::Cancel => RejectPendingAction::Cancel,
+        }
+    }
+}
 
 pub async fn handle_pending_rdy_to_execute(
     app: &Arc<AppContext>,
     positions: Vec<MtPosition<MtPositionPendingState>>,
     process_id: &str,
 ) {
-    let mut write = app.pending_execute_to_confirm_positions.write().await;
-
     let mut messages = vec![];
 
-    for pos in positions {
-        messages.push(PendingOrderNeedApproveEvent {
-            process_id: process_id.to_string(),
-            order: Some(crate::map_pending_to_sb_model(pos.clone())),
-        });
-        trade_log::trade_log!(
-            &pos.base_data.trader_id,
-            &pos.base_data.account_id,
-            process_id,
-            &pos.base_data.id,
-            "Detected pending order ready to execute",
-            service_sdk::my_telemetry::MyTelemetryContext::new(),
-            "position" = &pos
-        );
-        write.0.add_position(pos);
+    {
+        let mut write = app.pending_execute_to_confirm_positions.write().await;
+        let mut confirmation_deadlines = app.pending_confirmation_deadlines.lock().await;
+        let mut confirmations_journal = app.pending_confirmations_journal.lock().await;
+        let deadline = DateTimeAsMicroseconds::now().unix_microseconds
+            + app.pending_confirmation_timeout.as_micros() as i64;
+
+        for pos in positions {
+            messages.push(PendingOrderNeedApproveEvent {
+                process_id: process_id.to_string(),
+                order: Some(crate::map_pending_to_sb_model(pos.clone())),
+            });
+            trade_log::trade_log!(
+                &pos.base_data.trader_id,
+                &pos.base_data.account_id,
+                process_id,
+                &pos.base_data.id,
+                "Detected pending order ready to execute",
+                service_sdk::my_telemetry::MyTelemetryContext::new(),
+                "position" = &pos
+            );
+            confirmation_deadlines.arm(&pos.base_data.id, deadline);
+            confirmations_journal.add(&pos.base_data.id);
+            write.0.add_position(pos);
+        }
     }
 
-    app.pending_need_confirm_publisher
+    // The price loop does not wait for the bus, the orders are already armed and one that is
+    // never approved is handled by the confirmation timeout.
+    let app = app.clone();
+    tokio::spawn(async move {
+        publish_need_approve_events(&app, messages).await;
+    });
+}
+
+// Approval requests are retried until the bus accepts them.
+async fn publish_need_approve_events(
+    app: &AppContext,
+    messages: Vec<PendingOrderNeedApproveEvent>,
+) {
+    if messages.is_empty() {
+        return;
+    }
+
+    while let Err(err) = app
+        .pending_need_confirm_publisher
         .publish_messages(messages.iter().map(|x| (x, None)))
         .await
-        .unwrap();
+    {
+        println!(
+            "Failed to publish {} pending orders awaiting confirmation: {:?}",
+            messages.len(),
+            err
+        );
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// Orders restored from the journal on startup are asked for approval again, the approver
+// may have never seen the events published before the restart.
+pub async fn republish_awaiting_confirmations(app: Arc<AppContext>) {
+    let process_id = format!("startup.{}", Uuid::new_v4());
+
+    let messages: Vec<PendingOrderNeedApproveEvent> = {
+        let awaiting_ids = app.pending_confirmations_journal.lock().await.get_ids();
+        let read = app.pending_execute_to_confirm_positions.read().await;
+
+        awaiting_ids
+            .iter()
+            .filter_map(|id| read.0.get_by_id(id))
+            .map(|pos| PendingOrderNeedApproveEvent {
+                process_id: process_id.clone(),
+                order: Some(crate::map_pending_to_sb_model(pos.clone())),
+            })
+            .collect()
+    };
+
+    publish_need_approve_events(&app, messages).await;
 }
 
 pub async fn confirm_pending_execution(
     app: &Arc<AppContext>,
     position_id: &str,
-) -> Result<MtPosition<MtPositionActiveState>, MtEngineError> {
+) -> Result<MtPosition<MtPositionActiveState>, EngineError> {
     let process_id = Uuid::new_v4().to_string();
 
-    let target_position = app
-        .pending_execute_to_confirm_positions
-        .write()
-        .await
-        .0
-        .remove_position(position_id)
-        .ok_or(MtEngineError::PositionNotFound)?;
-
-    let active_position = {
+    // The order is taken out of awaiting confirmation only once it is filled. On a stale quote
+    // or a failed fill it stays, it can be confirmed again once the feed recovers or is handled
+    // by the confirmation timeout.
+    let (target_position, mut active_position) = {
         let prices_cache = app.active_prices_cache.read().await;
-        execute_pending_position(
+        let mut to_confirm_cache = app.pending_execute_to_confirm_positions.write().await;
+
+        let target_position = to_confirm_cache
+            .0
+            .get_by_id(position_id)
+            .ok_or(EngineError::PositionNotFound)?
+            .clone();
+
+        app.quote_age_limits
+            .check_position_price(&prices_cache, &target_position.base_data)?;
+
+        let active_position = execute_pending_position(
             target_position.clone(),
             &prices_cache,
             process_id.to_string(),
-        )
-    }?;
+        )?;
+
+        to_confirm_cache.0.remove_position(position_id);
+
+        (target_position, active_position)
+    };
+
+    app.pending_confirmation_deadlines
+        .lock()
+        .await
+        .disarm(position_id);
+    app.pending_confirmations_journal
+        .lock()
+        .await
+        .remove(position_id);
+
+    let bracket_exits = {
+        let mut pending_cache = app.pending_positions_cache.write().await;
+        take_bracket_exits(&mut pending_cache, &target_position.base_data)
+    };
+    apply_bracket_exits(&mut active_position, &bracket_exits);
 
     {
         let mut active_positions_cache = app.active_positions_cache.write().await;
@@ -76,31 +194,456 @@ pub async fn confirm_pending_execution(
     let pending_sb_model = crate::map_pending_to_sb_model(target_position);
     let active_sb_model = crate::map_active_to_sb_model(active_position.clone());
 
-    app.pending_positions_persistence_publisher
-        .publish(
-            &PendingPositionPersistenceEvent {
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
                 process_id: process_id.to_string(),
                 cancel: None,
+                cancel_reason: None,
                 create: None,
+                update: None,
                 execute: Some(pending_sb_model),
-            },
+            }),
             None,
         )
-        .await
-        .unwrap();
+        .await;
 
-    app.active_positions_persistence_publisher
-        .publish(
-            &PositionPersistenceEvent {
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
                 process_id: process_id.to_string(),
                 update_position: None,
                 close_position: None,
                 create_position: Some(active_sb_model),
-            },
+            }),
             None,
         )
+        .await;
+
+    // The exit legs live on as the take profit and stop loss of the position.
+    cancel_bracket_exits(app, bracket_exits, &process_id, &MyTelemetryContext::new()).await;
+
+    publish_position_updates(app, &[PositionUpdate::PendingExecuted(&active_position)]).await;
+
+    return Ok(active_position);
+}
+
+// The caller decides whether a rejected order stays in the market or is cancelled. The position
+// type of an order returned to pending is recalculated against the current price, the same way
+// a modified order is, so it is not picked again until the price crosses the desire price.
+pub async fn reject_pending_execution(
+    app: &Arc<AppContext>,
+    position_id: &str,
+    action: RejectPendingAction,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) -> Result<MtPosition<MtPositionPendingState>, EngineError> {
+    let prices_cache = app.active_prices_cache.read().await;
+    let mut pending_cache = app.pending_positions_cache.write().await;
+
+    let mut target_position = app
+        .pending_execute_to_confirm_positions
+        .write()
+        .await
+        .0
+        .remove_position(position_id)
+        .ok_or(EngineError::PositionNotFound)?;
+
+    app.pending_confirmation_deadlines
+        .lock()
+        .await
+        .disarm(position_id);
+    app.pending_confirmations_journal
+        .lock()
+        .await
+        .remove(position_id);
+
+    trade_log::trade_log!(
+        &target_position.base_data.trader_id,
+        &target_position.base_data.account_id,
+        process_id,
+        &target_position.base_data.id,
+        "Pending execution rejected",
+        telemetry.clone(),
+        "action" = &action,
+        "position" = &target_position
+    );
+
+    // The expiry timer skips orders awaiting confirmation, so a returned order gets its
+    // expiration armed again, or is cancelled if it expired in the meantime.
+    let expire_at = get_pending_expire_at(&target_position.base_data);
+    let is_expired = match expire_at {
+        Some(expire_at) => expire_at <= DateTimeAsMicroseconds::now().unix_microseconds,
+        None => false,
+    };
+
+    match action {
+        RejectPendingAction::ReturnToPending if !is_expired => {
+            if let Some(bid_ask) = prices_cache.get_by_id(&target_position.base_data.asset_pair) {
+                target_position.state.position_type = get_pending_position_type(
+                    get_close_price(&bid_ask, &target_position.base_data.side),
+                    target_position.state.desire_price,
+                    &target_position.base_data.side,
+                );
+            }
+
+            pending_cache.0.add_position(target_position.clone());
+
+            if let Some(expire_at) = expire_at {
+                app.pending_expirations
+                    .lock()
+                    .await
+                    .arm(&target_position.base_data.id, expire_at);
+            }
+
+            publish_position_updates(app, &[PositionUpdate::PendingModified(&target_position)])
+                .await;
+        }
+        _ => {
+            let cancel_reason = match action {
+                RejectPendingAction::ReturnToPending => PendingOrderCancelReason::Expired,
+                RejectPendingAction::Cancel => PendingOrderCancelReason::ExecutionRejected,
+            };
+
+            let exits = take_bracket_exits(&mut pending_cache, &target_position.base_data);
+            publish_position_updates(app, &[PositionUpdate::PendingCancelled(&target_position)])
+                .await;
+
+            app.pending_expirations
+                .lock()
+                .await
+                .disarm(&target_position.base_data.id);
+
+            app.persistence_outbox
+                .enqueue(
+                    OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
+                        process_id: process_id.to_string(),
+                        cancel: Some(crate::map_pending_to_sb_model(target_position.clone())),
+                        cancel_reason: Some(cancel_reason as i32),
+                        create: None,
+                        update: None,
+                        execute: None,
+                    }),
+                    Some(telemetry),
+                )
+                .await;
+
+            cancel_bracket_exits(app, exits, process_id, telemetry).await;
+        }
+    }
+
+    return Ok(target_position);
+}
+
+pub async fn expire_pending_confirmations(app: &Arc<AppContext>, now: DateTimeAsMicroseconds) {
+    let expired_ids = app
+        .pending_confirmation_deadlines
+        .lock()
+        .await
+        .take_expired(now.unix_microseconds);
+
+    let telemetry = MyTelemetryContext::new();
+
+    for position_id in expired_ids {
+        let process_id = format!(
+            "{}{}",
+            PENDING_CONFIRMATION_TIMEOUT_PROCESS_ID_PREFIX,
+            Uuid::new_v4()
+        );
+
+        let _ = reject_pending_execution(
+            app,
+            &position_id,
+            app.pending_confirmation_timeout_action,
+            &process_id,
+            &telemetry,
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use service_sdk::{
+        my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
+    };
+
+    use std::collections::HashMap;
+
+    use trading_sdk::mt_engine::{
+        is_ready_to_execute_pending_position, MtPosition, MtPositionPendingState,
+    };
+
+    use crate::{
+        handle_pending_positions_update,
+        test_utils::{create_test_app, create_test_bid_ask, create_test_pending_position},
+        PendingGroup, PendingGroupRole, EXPIRE_AT_METADATA_KEY,
+    };
+
+    use super::{
+        confirm_pending_execution, expire_pending_confirmations, reject_pending_execution,
+        RejectPendingAction,
+    };
+
+    fn create_bracket_member(
+        id: &str,
+        role: PendingGroupRole,
+    ) -> MtPosition<MtPositionPendingState> {
+        let mut position = create_test_pending_position(id, "trader", "account");
+        let mut metadata = HashMap::new();
+        PendingGroup::write_metadata(
+            Some(PendingGroup {
+                id: "bracket".to_string(),
+                role,
+            }),
+            &mut metadata,
+        );
+        position.base_data.metadata = Some(metadata);
+        return position;
+    }
+
+    #[tokio::test]
+    async fn test_reject_returns_to_pending() {
+        let app = create_test_app();
+
+        app.pending_execute_to_confirm_positions
+            .write()
+            .await
+            .0
+            .add_position(create_test_pending_position("pending", "trader", "account"));
+
+        reject_pending_execution(
+            &app,
+            "pending",
+            RejectPendingAction::ReturnToPending,
+            "process",
+            &MyTelemetryContext::new(),
+        )
         .await
         .unwrap();
 
-    return Ok(active_position);
+        assert!(app
+            .pending_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_some());
+        assert_eq!(app.persistence_outbox.len().await, 0);
+    }
+
+    fn create_expiring_pending(id: &str, expire_at: i64) -> MtPosition<MtPositionPendingState> {
+        let mut position = create_test_pending_position(id, "trader", "account");
+        position.base_data.metadata = Some(HashMap::from([(
+            EXPIRE_AT_METADATA_KEY.to_string(),
+            expire_at.to_string(),
+        )]));
+        return position;
+    }
+
+    #[tokio::test]
+    async fn test_returned_pending_gets_expiration_back() {
+        let app = create_test_app();
+        let now = DateTimeAsMicroseconds::now().unix_microseconds;
+
+        {
+            let mut to_confirm = app.pending_execute_to_confirm_positions.write().await;
+            to_confirm
+                .0
+                .add_position(create_expiring_pending("alive", now + 60_000_000));
+            to_confirm
+                .0
+                .add_position(create_expiring_pending("expired", now - 1));
+        }
+
+        for id in ["alive", "expired"] {
+            reject_pending_execution(
+                &app,
+                id,
+                RejectPendingAction::ReturnToPending,
+                "process",
+                &MyTelemetryContext::new(),
+            )
+            .await
+            .unwrap();
+        }
+
+        {
+            let pending_cache = app.pending_positions_cache.read().await;
+            assert!(pending_cache.0.get_by_id("alive").is_some());
+            assert!(pending_cache.0.get_by_id("expired").is_none());
+        }
+
+        let mut expirations = app.pending_expirations.lock().await;
+        assert_eq!(expirations.get_count(), 1);
+        assert_eq!(
+            expirations.take_expired(now + 60_000_000),
+            vec!["alive".to_string()]
+        );
+        assert_eq!(app.persistence_outbox.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_timeout_cancels() {
+        let app = create_test_app();
+
+        app.pending_execute_to_confirm_positions
+            .write()
+            .await
+            .0
+            .add_position(create_test_pending_position("pending", "trader", "account"));
+        app.pending_confirmation_deadlines
+            .lock()
+            .await
+            .arm("pending", 100);
+
+        expire_pending_confirmations(&app, DateTimeAsMicroseconds::new(200)).await;
+
+        assert!(app
+            .pending_execute_to_confirm_positions
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_none());
+        assert!(app
+            .pending_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_none());
+        assert_eq!(app.persistence_outbox.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_bracket_entry_cancels_exits() {
+        let app = create_test_app();
+
+        app.pending_execute_to_confirm_positions
+            .write()
+            .await
+            .0
+            .add_position(create_bracket_member(
+                "entry",
+                PendingGroupRole::BracketEntry,
+            ));
+
+        {
+            let mut pending_cache = app.pending_positions_cache.write().await;
+            pending_cache.0.add_position(create_bracket_member(
+                "tp",
+                PendingGroupRole::BracketTakeProfit,
+            ));
+            pending_cache.0.add_position(create_bracket_member(
+                "sl",
+                PendingGroupRole::BracketStopLoss,
+            ));
+        }
+
+        reject_pending_execution(
+            &app,
+            "entry",
+            RejectPendingAction::Cancel,
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await
+        .unwrap();
+
+        let pending_cache = app.pending_positions_cache.read().await;
+        assert!(pending_cache.0.get_by_id("tp").is_none());
+        assert!(pending_cache.0.get_by_id("sl").is_none());
+        assert_eq!(app.persistence_outbox.len().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_returned_pending_is_not_selected_on_same_price() {
+        let app = create_test_app();
+        let bid_ask = create_test_bid_ask(0.99, 0.99);
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(0.99, 0.99));
+
+        let position = create_test_pending_position("pending", "trader", "account");
+        assert!(is_ready_to_execute_pending_position(&position, &bid_ask));
+
+        app.pending_execute_to_confirm_positions
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        reject_pending_execution(
+            &app,
+            "pending",
+            RejectPendingAction::ReturnToPending,
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await
+        .unwrap();
+
+        handle_pending_positions_update(&app, &bid_ask, "process", &MyTelemetryContext::new())
+            .await;
+
+        assert!(app
+            .pending_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_some());
+        assert!(app
+            .pending_execute_to_confirm_positions
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_confirmation_keeps_order_awaiting() {
+        let app = create_test_app();
+
+        app.pending_execute_to_confirm_positions
+            .write()
+            .await
+            .0
+            .add_position(create_test_pending_position("pending", "trader", "account"));
+        app.pending_confirmation_deadlines
+            .lock()
+            .await
+            .arm("pending", 100);
+        app.pending_confirmations_journal
+            .lock()
+            .await
+            .add("pending");
+
+        let result = confirm_pending_execution(&app, "pending").await;
+
+        assert!(result.is_err());
+        assert!(app
+            .pending_execute_to_confirm_positions
+            .read()
+            .await
+            .0
+            .get_by_id("pending")
+            .is_some());
+        assert_eq!(
+            app.pending_confirmations_journal.lock().await.get_ids(),
+            vec!["pending".to_string()]
+        );
+        assert_eq!(
+            app.pending_confirmation_deadlines
+                .lock()
+                .await
+                .take_expired(200),
+            vec!["pending".to_string()]
+        );
+        assert_eq!(app.persistence_outbox.len().await, 0);
+    }
 }
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use cfd_engine_sb_contracts::{PendingOrderCancelReason, PendingPositionPersistenceEvent};
+use service_sdk::{
+    my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
+};
+use uuid::Uuid;
+
+use crate::{
+    cancel_bracket_exits, map_pending_to_sb_model, publish_position_updates, take_bracket_exits,
+    AppContext, OutboxMessage, PositionUpdate,
+};
+
+pub async fn expire_pending_positions(app: &Arc<AppContext>, now: DateTimeAsMicroseconds) {
+    let expired_ids = {
+        let mut expirations = app.pending_expirations.lock().await;
+        let expired_ids = expirations.take_expired(now.unix_microseconds);
+        service_sdk::metrics::gauge!("pending_expirations_count")
+            .set(expirations.get_count() as f64);
+        expired_ids
+    };
+
+    if expired_ids.is_empty() {
+        return;
+    }
+
+    let expired_positions = {
+        let mut pending_cache = app.pending_positions_cache.write().await;
+        expired_ids
+            .iter()
+            .filter_map(|id| pending_cache.0.remove_position(id))
+            .map(|position| {
+                let exits = take_bracket_exits(&mut pending_cache, &position.base_data);
+                (position, exits)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let telemetry = MyTelemetryContext::new();
+
+    for (position, exits) in expired_positions {
+        let process_id = Uuid::new_v4().to_string();
+
+        trade_log::trade_log!(
+            &position.base_data.trader_id,
+            &position.base_data.account_id,
+            &process_id,
+            &position.base_data.id,
+            "Pending position expired",
+            telemetry.clone(),
+            "position" = &position
+        );
+
+        publish_position_updates(app, &[PositionUpdate::PendingCancelled(&position)]).await;
+
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
+                    process_id: process_id.clone(),
+                    cancel: Some(map_pending_to_sb_model(position)),
+                    cancel_reason: Some(PendingOrderCancelReason::Expired as i32),
+                    execute: None,
+                    create: None,
+                    update: None,
+                }),
+                Some(&telemetry),
+            )
+            .await;
+
+        cancel_bracket_exits(app, exits, &process_id, &telemetry).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+
+    use crate::test_utils::{create_test_app, create_test_pending_position};
+
+    use super::expire_pending_positions;
+
+    #[tokio::test]
+    async fn test_expired_pending_is_removed() {
+        let app = create_test_app();
+
+        {
+            let mut pending_cache = app.pending_positions_cache.write().await;
+            pending_cache
+                .0
+                .add_position(create_test_pending_position("expired", "trader", "account"));
+            pending_cache
+                .0
+                .add_position(create_test_pending_position("alive", "trader", "account"));
+        }
+
+        {
+            let mut expirations = app.pending_expirations.lock().await;
+            expirations.arm("expired", 100);
+            expirations.arm("alive", 300);
+        }
+
+        expire_pending_positions(&app, DateTimeAsMicroseconds::new(200)).await;
+
+        let pending_cache = app.pending_positions_cache.read().await;
+        assert!(pending_cache.0.get_by_id("expired").is_none());
+        assert!(pending_cache.0.get_by_id("alive").is_some());
+        assert_eq!(app.persistence_outbox.len().await, 1);
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use service_sdk::{
+    my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
+};
+use trading_sdk::mt_engine::{
+    update_active_position_rate, update_position_pl, MtBidAsk, MtBidAskCache, MtPosition,
+    MtPositionActiveState, MtPositionBaseData,
+};
+
+use crate::{
+    map_active_to_sb_model, publish_position_updates, utils::get_open_price, AppContext,
+    EngineError, OutboxMessage, PositionUpdate,
+};
+
+pub async fn increase_position(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+    position_id: &str,
+    invest_amount: f64,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) -> Result<MtPosition<MtPositionActiveState>, EngineError> {
+    let prices_cache = app.active_prices_cache.read().await;
+    let mut cache = app.active_positions_cache.write().await;
+
+    let (asset_pair, base_collateral_price) = {
+        let position = cache
+            .0
+            .get_by_id(position_id)
+            .ok_or(EngineError::PositionNotFound)?;
+
+        app.quote_age_limits
+            .check_position_price(&prices_cache, &position.base_data)?;
+
+        let base_collateral_price =
+            get_base_collateral_open_price(&prices_cache, &position.base_data)
+                .ok_or(EngineError::NoLiquidity)?;
+
+        (position.base_data.asset_pair.clone(), base_collateral_price)
+    };
+
+    let bid_ask = prices_cache
+        .get_by_id(&asset_pair)
+        .ok_or(EngineError::NoLiquidity)?;
+
+    let updated_position = cache
+        .0
+        .update_position(position_id, |x| {
+            if let Some(src) = x {
+                increase_active_position(
+                    src,
+                    invest_amount,
+                    bid_ask.as_ref(),
+                    base_collateral_price,
+                    process_id,
+                );
+                return Some(src.clone());
+            }
+
+            return None;
+        })
+        .ok_or(EngineError::PositionNotFound)?;
+
+    trade_log::trade_log!(
+        trader_id,
+        account_id,
+        process_id,
+        position_id,
+        "Executing increase position",
+        telemetry.clone(),
+        "invest_amount" = &invest_amount,
+        "bid_ask" = bid_ask.as_ref(),
+        "updated_position" = &updated_position
+    );
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: Some(map_active_to_sb_model(updated_position.clone())),
+                close_position: None,
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    publish_position_updates(app, &[PositionUpdate::Updated(&updated_position)]).await;
+
+    return Ok(updated_position);
+}
+
+// The base collateral price of the open, a base currency which is the collateral itself needs
+// no conversion.
+pub fn get_base_collateral_open_price(
+    prices_cache: &MtBidAskCache,
+    base_data: &MtPositionBaseData,
+) -> Option<f64> {
+    if base_data.base == base_data.collateral {
+        return Some(1.0);
+    }
+
+    if let Some(bid_ask) = prices_cache.get_base_quote(&base_data.base, &base_data.collateral) {
+        return Some(get_open_price(&bid_ask, &base_data.side));
+    }
+
+    return prices_cache
+        .get_base_quote(&base_data.collateral, &base_data.base)
+        .map(|bid_ask| 1.0 / get_open_price(&bid_ask, &base_data.side));
+}
+
+// Every part keeps its base volume (invest amount * leverage / base collateral price). The
+// merged position gets the volume weighted open price and the base collateral price which
+// adds the volumes up, so its P&L equals the sum of the P&L of the parts at any price.
+pub fn increase_active_position(
+    position: &mut MtPosition<MtPositionActiveState>,
+    invest_amount: f64,
+    bid_ask: &MtBidAsk,
+    base_collateral_price: f64,
+    process_id: &str,
+) {
+    let leverage = position.base_data.leverage;
+    let fill_price = get_open_price(bid_ask, &position.base_data.side);
+    let open_data = &mut position.state.open_data;
+
+    let current_volume =
+        position.base_data.invest_amount * leverage / open_data.base_collateral_open_price;
+    let added_volume = invest_amount * leverage / base_collateral_price;
+    let volume = current_volume + added_volume;
+
+    open_data.asset_open_price =
+        (open_data.asset_open_price * current_volume + fill_price * added_volume) / volume;
+    position.base_data.invest_amount += invest_amount;
+    open_data.base_collateral_open_price = position.base_data.invest_amount * leverage / volume;
+
+    position.base_data.last_update_date = DateTimeAsMicroseconds::now();
+    position.base_data.last_update_process_id = process_id.to_string();
+
+    update_active_position_rate(position, bid_ask);
+    update_position_pl(position);
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::{update_active_position_rate, update_position_pl};
+
+    use crate::test_utils::{create_test_active_position, create_test_bid_ask};
+
+    use super::increase_active_position;
+
+    #[test]
+    fn test_increase_recalculates_open_price() {
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.state.swaps.total = -3.0;
+
+        increase_active_position(
+            &mut position,
+            100.0,
+            &create_test_bid_ask(1.2, 1.2),
+            1.0,
+            "process",
+        );
+
+        assert_eq!(position.base_data.invest_amount, 200.0);
+        assert!((position.state.open_data.asset_open_price - 1.15).abs() < 1e-12);
+        assert!((position.state.open_data.base_collateral_open_price - 1.0).abs() < 1e-12);
+        assert_eq!(position.state.swaps.total, -3.0);
+        assert_eq!(position.base_data.last_update_process_id, "process");
+    }
+
+    #[test]
+    fn test_increased_profit_is_sum_of_parts() {
+        let mut first = create_test_active_position("first", "trader", "account");
+        let mut second = create_test_active_position("second", "trader", "account");
+        second.base_data.invest_amount = 50.0;
+        second.state.open_data.asset_open_price = 1.2;
+        second.state.open_data.base_collateral_open_price = 1.25;
+
+        let mut merged = first.clone();
+        increase_active_position(
+            &mut merged,
+            50.0,
+            &create_test_bid_ask(1.2, 1.2),
+            1.25,
+            "process",
+        );
+
+        let moved_bid_ask = create_test_bid_ask(1.3, 1.3);
+        for position in [&mut first, &mut second, &mut merged] {
+            update_active_position_rate(position, &moved_bid_ask);
+            update_position_pl(position);
+        }
+
+        assert!(first.state.profit > 0.0);
+        assert!((merged.state.profit - (first.state.profit + second.state.profit)).abs() < 1e-9);
+    }
+}
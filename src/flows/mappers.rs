@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use cfd_engine_sb_contracts::{
     OrderBidAskSbModel, OrderSbModel, OrderSide, OrderSwap, PendingOrderSbModel,
 };
@@ -11,7 +13,8 @@ use crate::{
     position_manager_grpc::PositionManagerPositionSide,
     position_manager_persistence::{
         PositionManagerPersistenceActivePositionGrpcModel, PositionManagerPersistenceBidAsk,
-        PositionManagerPersistencePendingPositionGrpcModel,
+        PositionManagerPersistencePendingPositionGrpcModel, PositionManagerPersistencePositionSide,
+        PositionManagerPositionSwapGrpcModel,
     },
 };
 
@@ -341,3 +344,98 @@ pub async fn get_active_prices(
 
     return (asset_bid_ask.as_ref().clone(), quote_collateral);
 }
+
+fn map_sb_side_to_persistence(side: OrderSide) -> PositionManagerPersistencePositionSide {
+    match side {
+        OrderSide::Buy => PositionManagerPersistencePositionSide::Buy,
+        OrderSide::Sell => PositionManagerPersistencePositionSide::Sell,
+    }
+}
+
+fn map_sb_bid_ask_to_persistence(src: OrderBidAskSbModel) -> PositionManagerPersistenceBidAsk {
+    PositionManagerPersistenceBidAsk {
+        asset_pair: src.id,
+        bid: src.bid,
+        ask: src.ask,
+        date_time_unix_timestamp_milis: src.date,
+        base: src.base,
+        quote: src.quote,
+    }
+}
+
+// The bus models carry no metadata, so it is left empty here.
+pub fn map_active_sb_to_persistence(
+    src: OrderSbModel,
+) -> PositionManagerPersistenceActivePositionGrpcModel {
+    PositionManagerPersistenceActivePositionGrpcModel {
+        side: map_sb_side_to_persistence(src.side()) as i32,
+        id: src.id,
+        account_id: src.account_id,
+        trader_id: src.trader_id,
+        asset_pair: src.asset_pair,
+        invest_amount: src.invest_amount,
+        leverage: src.leverage,
+        stop_out_percent: src.stop_out_percent,
+        create_process_id: src.create_process_id,
+        create_date_unix_timestamp_milis: src.create_date,
+        last_update_process_id: src.last_update_process_id,
+        last_update_date: src.last_update_date,
+        tp_in_profit: src.tp_in_currency,
+        sl_in_profit: src.sl_in_currency,
+        tp_in_asset_price: src.tp_in_instrument_price,
+        sl_in_asset_price: src.sl_in_instrument_price,
+        collateral: src.collateral_currency,
+        base: src.base,
+        quote: src.quote,
+        asset_open_price: src.asset_open_price,
+        asset_open_bid_ask: src.asset_open_bid_ask.map(map_sb_bid_ask_to_persistence),
+        collateral_base_open_price: src.base_collateral_open_price,
+        collateral_base_open_bid_ask: src
+            .base_collateral_open_bid_ask
+            .map(map_sb_bid_ask_to_persistence),
+        open_process_id: src.open_process_id,
+        open_date_unix_timestamp_milis: src.open_date,
+        swaps: src
+            .swaps
+            .into_iter()
+            .map(|x| PositionManagerPositionSwapGrpcModel {
+                amount: x.amount,
+                date: x.date,
+            })
+            .collect(),
+        topping_up_percent: src.topping_up_percent,
+        metadata: HashMap::new(),
+        margin_call_percent: src.margin_call_percent,
+        reserved_fund_for_topping_up: src.topping_up_amount,
+    }
+}
+
+pub fn map_pending_sb_to_persistence(
+    src: PendingOrderSbModel,
+) -> PositionManagerPersistencePendingPositionGrpcModel {
+    PositionManagerPersistencePendingPositionGrpcModel {
+        side: map_sb_side_to_persistence(src.side()) as i32,
+        id: src.id,
+        account_id: src.account_id,
+        trader_id: src.trader_id,
+        asset_pair: src.asset_pair,
+        invest_amount: src.invest_amount,
+        leverage: src.leverage,
+        stop_out_percent: src.stop_out_percent,
+        create_process_id: src.create_process_id,
+        create_date_unix_timestamp_milis: src.create_date,
+        last_update_process_id: src.last_update_process_id,
+        last_update_date: src.last_update_date,
+        tp_in_profit: src.tp_in_currency,
+        sl_in_profit: src.sl_in_currency,
+        tp_in_asset_price: src.tp_in_instrument_price,
+        sl_in_asset_price: src.sl_in_instrument_price,
+        collateral: src.collateral_currency,
+        base: src.base,
+        quote: src.quote,
+        desire_price: src.desire_price,
+        topping_up_percent: src.topping_up_percent,
+        metadata: HashMap::new(),
+        margin_call_percent: src.margin_call_percent,
+    }
+}
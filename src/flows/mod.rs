@@ -8,6 +8,24 @@ mod cancel_pending;
 mod execute_pending_positions;
 mod handle_position_margin_call;
 mod process_topping_up_refund;
+mod position_ids;
+mod partial_close_position;
+mod increase_position;
+mod netting;
+mod close_by;
+mod reverse_position;
+mod trailing_stop;
+mod break_even_stop;
+mod expire_pending;
+mod modify_pending;
+mod pending_groups;
+mod slippage;
+mod stale_prices;
+mod account_margin;
+mod account_summary;
+mod position_updates;
+mod trigger_prices;
+mod preview_open_position;
 
 pub use startup::*;
 pub use close_position::*;
@@ -19,3 +37,21 @@ pub use cancel_pending::*;
 pub use execute_pending_positions::*;
 pub use handle_position_margin_call::*;
 pub use process_topping_up_refund::*;
+pub use position_ids::*;
+pub use partial_close_position::*;
+pub use increase_position::*;
+pub use netting::*;
+pub use close_by::*;
+pub use reverse_position::*;
+pub use trailing_stop::*;
+pub use break_even_stop::*;
+pub use expire_pending::*;
+pub use modify_pending::*;
+pub use pending_groups::*;
+pub use slippage::*;
+pub use stale_prices::*;
+pub use account_margin::*;
+pub use account_summary::*;
+pub use position_updates::*;
+pub use trigger_prices::*;
+pub use preview_open_position::*;
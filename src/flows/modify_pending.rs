@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use cfd_engine_sb_contracts::PendingPositionPersistenceEvent;
+use service_sdk::{
+    my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
+};
+use trading_sdk::mt_engine::{
+    get_close_price, get_pending_position_type, sanitize_sl_tp, MtPosition, MtPositionPendingState,
+};
+
+use crate::{
+    map_pending_to_sb_model, position_manager_grpc::PositionManagerModifyPendingGrpcRequest,
+    publish_position_updates, AppContext, EngineError, OutboxMessage, PositionUpdate,
+};
+
+// The order keeps its id and create date, only the execution terms are replaced. The
+// position type is recalculated because a new desire price can move the order to the other
+// side of the market.
+pub async fn modify_pending(
+    app: &Arc<AppContext>,
+    request: &PositionManagerModifyPendingGrpcRequest,
+    telemetry: &MyTelemetryContext,
+) -> Result<MtPosition<MtPositionPendingState>, EngineError> {
+    let prices_cache = app.active_prices_cache.read().await;
+    let mut pending_cache = app.pending_positions_cache.write().await;
+
+    let asset_pair = pending_cache
+        .0
+        .get_by_id(&request.id)
+        .ok_or(EngineError::PositionNotFound)?
+        .base_data
+        .asset_pair
+        .clone();
+
+    let bid_ask = prices_cache
+        .get_by_id(&asset_pair)
+        .ok_or(EngineError::NoLiquidity)?;
+
+    let modified = pending_cache
+        .0
+        .update_position(&request.id, |x| {
+            let src = x?;
+            src.base_data.invest_amount = request.invest_amount;
+            src.base_data.leverage = request.leverage;
+            src.base_data.sl_price = request.sl_in_asset_price;
+            src.base_data.tp_price = request.tp_in_asset_price;
+            src.base_data.sl_profit = request.sl_in_profit;
+            src.base_data.tp_profit = request.tp_in_profit;
+            src.base_data.last_update_date = DateTimeAsMicroseconds::now();
+            src.base_data.last_update_process_id = request.process_id.clone();
+            sanitize_sl_tp(&mut src.base_data);
+
+            src.state.desire_price = request.desire_price;
+            src.state.position_type = get_pending_position_type(
+                get_close_price(&bid_ask, &src.base_data.side),
+                request.desire_price,
+                &src.base_data.side,
+            );
+
+            return Some(src.clone());
+        })
+        .ok_or(EngineError::PositionNotFound)?;
+
+    trade_log::trade_log!(
+        &modified.base_data.trader_id,
+        &modified.base_data.account_id,
+        &request.process_id,
+        &modified.base_data.id,
+        "Pending position modified",
+        telemetry.clone(),
+        "modified_position" = &modified
+    );
+
+    let sb_event = PendingPositionPersistenceEvent {
+        process_id: request.process_id.clone(),
+        cancel: None,
+        cancel_reason: None,
+        execute: None,
+        create: None,
+        update: Some(map_pending_to_sb_model(modified.clone())),
+    };
+
+    app.persistence_outbox
+        .enqueue(OutboxMessage::PendingPosition(sb_event), Some(telemetry))
+        .await;
+
+    publish_position_updates(app, &[PositionUpdate::PendingModified(&modified)]).await;
+
+    return Ok(modified);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        position_manager_grpc::PositionManagerModifyPendingGrpcRequest,
+        test_utils::{create_test_app, create_test_bid_ask, create_test_pending_position},
+        OutboxMessage,
+    };
+
+    use super::modify_pending;
+
+    #[tokio::test]
+    async fn test_modify_pending_keeps_id() {
+        let app = create_test_app();
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.1, 1.1));
+
+        let position = create_test_pending_position("pending", "trader", "account");
+        let create_date = position.base_data.crate_date.unix_microseconds;
+        app.pending_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        let request = PositionManagerModifyPendingGrpcRequest {
+            id: "pending".to_string(),
+            process_id: "modify".to_string(),
+            account_id: "account".to_string(),
+            trader_id: "trader".to_string(),
+            desire_price: 1.2,
+            invest_amount: 50.0,
+            leverage: 20.0,
+            tp_in_profit: None,
+            sl_in_profit: Some(-10.0),
+            tp_in_asset_price: None,
+            sl_in_asset_price: None,
+        };
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+        let modified = modify_pending(&app, &request, &telemetry).await.unwrap();
+
+        assert_eq!(modified.base_data.id, "pending");
+        assert_eq!(modified.base_data.crate_date.unix_microseconds, create_date);
+        assert_eq!(modified.base_data.invest_amount, 50.0);
+        assert_eq!(modified.base_data.leverage, 20.0);
+        assert_eq!(modified.state.desire_price, 1.2);
+        assert_eq!(app.persistence_outbox.len().await, 1);
+
+        match app.persistence_outbox.peek().await.unwrap().message {
+            OutboxMessage::PendingPosition(event) => {
+                assert!(event.create.is_none());
+                assert_eq!(event.update.unwrap().id, "pending");
+            }
+            _ => panic!("Unexpected outbox message"),
+        }
+    }
+}
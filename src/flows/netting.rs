@@ -0,0 +1,523 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use service_sdk::my_telemetry::MyTelemetryContext;
+use trading_sdk::{
+    core::EngineCacheQueryBuilder,
+    mt_engine::{
+        convert_position_to_closed, make_active_position, sanitize_sl_tp,
+        update_active_position_rate, update_position_pl, ActivePositionsCache, MtBidAskCache,
+        MtPosition, MtPositionActiveState, MtPositionCloseReason, MtPositionClosedState,
+        MtPositionOpenCommand,
+    },
+};
+
+use crate::{
+    apply_closed_position_profit, enqueue_split_part_create, get_base_collateral_open_price,
+    increase_active_position, map_active_to_sb_model, map_closed_to_sb,
+    set_position_break_even_stop, set_position_trailing_stop, split_active_position,
+    utils::is_same_side, AppContext, BreakEvenStop, EngineError, OpenPositionResult, OutboxMessage,
+    TrailingStop,
+};
+
+const NETTING_AMOUNT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountMode {
+    Hedging,
+    Netting,
+}
+
+// The account group is resolved from the settings only, the mode must not depend on anything
+// the caller sends with the request.
+pub struct AccountModes {
+    netting_accounts: HashSet<String>,
+    netting_account_groups: HashSet<String>,
+    account_groups: HashMap<String, String>,
+}
+
+impl AccountModes {
+    pub fn new(
+        netting_accounts: &[String],
+        netting_account_groups: &[String],
+        account_groups: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            netting_accounts: netting_accounts.iter().cloned().collect(),
+            netting_account_groups: netting_account_groups.iter().cloned().collect(),
+            account_groups,
+        }
+    }
+
+    pub fn get_mode(&self, account_id: &str) -> AccountMode {
+        if self.netting_accounts.contains(account_id) {
+            return AccountMode::Netting;
+        }
+
+        if let Some(account_group) = self.account_groups.get(account_id) {
+            if self.netting_account_groups.contains(account_group) {
+                return AccountMode::Netting;
+            }
+        }
+
+        return AccountMode::Hedging;
+    }
+}
+
+pub fn find_netting_position_id(
+    cache: &ActivePositionsCache,
+    trader_id: &str,
+    account_id: &str,
+    asset_pair: &str,
+) -> Option<String> {
+    let query = EngineCacheQueryBuilder::new()
+        .with_client(trader_id)
+        .with_account(account_id);
+
+    return cache
+        .0
+        .query_positions(query)
+        .into_iter()
+        .find(|x| x.base_data.asset_pair == asset_pair)
+        .map(|x| x.base_data.id.clone());
+}
+
+// Protection sent with a same-side open replaces the one of the merged position, protection
+// which is not sent is kept.
+fn apply_open_protection(
+    position: &mut MtPosition<MtPositionActiveState>,
+    open_command: &MtPositionOpenCommand,
+) {
+    if open_command.sl_price.is_some() || open_command.sl_profit.is_some() {
+        position.base_data.sl_price = open_command.sl_price;
+        position.base_data.sl_profit = open_command.sl_profit;
+    }
+
+    if open_command.tp_price.is_some() || open_command.tp_profit.is_some() {
+        position.base_data.tp_price = open_command.tp_price;
+        position.base_data.tp_profit = open_command.tp_profit;
+    }
+
+    let metadata = open_command.metadata.as_ref();
+
+    if let Some(trailing_stop) = TrailingStop::from_metadata(metadata) {
+        set_position_trailing_stop(&mut position.base_data.metadata, Some(trailing_stop));
+    }
+
+    if let Some(break_even) = BreakEvenStop::from_metadata(metadata) {
+        set_position_break_even_stop(&mut position.base_data.metadata, Some(break_even));
+    }
+
+    sanitize_sl_tp(&mut position.base_data);
+}
+
+// Both sides share the leverage, so the invest amounts net out directly.
+pub async fn open_netting_position(
+    app: &Arc<AppContext>,
+    positions_cache: &mut ActivePositionsCache,
+    prices_cache: &MtBidAskCache,
+    existing_position_id: &str,
+    open_command: MtPositionOpenCommand,
+    telemetry: &MyTelemetryContext,
+) -> Result<OpenPositionResult, EngineError> {
+    let mut existing = positions_cache
+        .0
+        .remove_position(existing_position_id)
+        .ok_or(EngineError::PositionNotFound)?;
+
+    // Netting amounts of another leverage would rescale the margin of one side, so the
+    // instrument can only be traded with another leverage once the position is closed.
+    if open_command.leverage != existing.base_data.leverage {
+        positions_cache.0.add_position(existing);
+        return Err(EngineError::LeverageMismatch);
+    }
+
+    let bid_ask = match prices_cache.get_by_id(&existing.base_data.asset_pair) {
+        Some(bid_ask) => bid_ask,
+        None => {
+            positions_cache.0.add_position(existing);
+            return Err(EngineError::NoLiquidity);
+        }
+    };
+
+    if let Err(err) = app
+        .quote_age_limits
+        .check_position_price(prices_cache, &existing.base_data)
+    {
+        positions_cache.0.add_position(existing);
+        return Err(err);
+    }
+
+    let process_id = open_command.process_id.clone();
+    let netted_amount = open_command.invest_amount;
+
+    if is_same_side(&existing.base_data.side, &open_command.side) {
+        let base_collateral_price =
+            match get_base_collateral_open_price(prices_cache, &existing.base_data) {
+                Some(price) => price,
+                None => {
+                    positions_cache.0.add_position(existing);
+                    return Err(EngineError::NoLiquidity);
+                }
+            };
+
+        increase_active_position(
+            &mut existing,
+            netted_amount,
+            &bid_ask,
+            base_collateral_price,
+            &process_id,
+        );
+        apply_open_protection(&mut existing, &open_command);
+        positions_cache.0.add_position(existing.clone());
+
+        trade_log::trade_log!(
+            &open_command.trader_id,
+            &open_command.account_id,
+            &process_id,
+            &existing.base_data.id,
+            "Netting open increased existing position",
+            telemetry.clone(),
+            "open_command" = &open_command,
+            "active_position" = &existing
+        );
+
+        enqueue_update(app, &process_id, &existing, telemetry).await;
+
+        return Ok(OpenPositionResult {
+            position: Some(existing),
+            closed_positions: vec![],
+        });
+    }
+
+    update_active_position_rate(&mut existing, &bid_ask);
+    update_position_pl(&mut existing);
+
+    let existing_invest_amount = existing.base_data.invest_amount;
+
+    if netted_amount < existing_invest_amount - NETTING_AMOUNT_TOLERANCE {
+        let (closed_part, remaining) = split_active_position(existing, netted_amount, &process_id);
+
+        let closed = convert_position_to_closed(
+            closed_part.clone(),
+            MtPositionCloseReason::ClientCommand,
+            process_id.clone(),
+        );
+
+        positions_cache.0.add_position(remaining.clone());
+
+        trade_log::trade_log!(
+            &open_command.trader_id,
+            &open_command.account_id,
+            &process_id,
+            &remaining.base_data.id,
+            "Netting open reduced existing position",
+            telemetry.clone(),
+            "open_command" = &open_command,
+            "closed_part" = &closed,
+            "remaining_position" = &remaining
+        );
+
+        enqueue_split_part_create(app, &process_id, &closed_part, telemetry).await;
+        enqueue_close(app, &process_id, &closed, telemetry).await;
+        apply_closed_position_profit(app, &closed).await;
+        enqueue_update(app, &process_id, &remaining, telemetry).await;
+
+        return Ok(OpenPositionResult {
+            position: Some(remaining),
+            closed_positions: vec![closed],
+        });
+    }
+
+    if netted_amount <= existing_invest_amount + NETTING_AMOUNT_TOLERANCE {
+        let closed = convert_position_to_closed(
+            existing,
+            MtPositionCloseReason::ClientCommand,
+            process_id.clone(),
+        );
+
+        trade_log::trade_log!(
+            &open_command.trader_id,
+            &open_command.account_id,
+            &process_id,
+            &closed.base_data.id,
+            "Netting open closed existing position",
+            telemetry.clone(),
+            "open_command" = &open_command,
+            "closed_position" = &closed
+        );
+
+        enqueue_close(app, &process_id, &closed, telemetry).await;
+        apply_closed_position_profit(app, &closed).await;
+
+        return Ok(OpenPositionResult {
+            position: None,
+            closed_positions: vec![closed],
+        });
+    }
+
+    let mut reverse_command = open_command.clone();
+    reverse_command.invest_amount = netted_amount - existing_invest_amount;
+
+    let position = match make_active_position(reverse_command, prices_cache) {
+        Ok(position) => position,
+        Err(err) => {
+            positions_cache.0.add_position(existing);
+            return Err(err.into());
+        }
+    };
+
+    let closed = convert_position_to_closed(
+        existing,
+        MtPositionCloseReason::ClientCommand,
+        process_id.clone(),
+    );
+
+    positions_cache.0.add_position(position.clone());
+
+    trade_log::trade_log!(
+        &open_command.trader_id,
+        &open_command.account_id,
+        &process_id,
+        &position.base_data.id,
+        "Netting open reversed existing position",
+        telemetry.clone(),
+        "open_command" = &open_command,
+        "closed_position" = &closed,
+        "active_position" = &position
+    );
+
+    enqueue_close(app, &process_id, &closed, telemetry).await;
+    apply_closed_position_profit(app, &closed).await;
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.clone(),
+                update_position: None,
+                close_position: None,
+                create_position: Some(map_active_to_sb_model(position.clone())),
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    return Ok(OpenPositionResult {
+        position: Some(position),
+        closed_positions: vec![closed],
+    });
+}
+
+async fn enqueue_close(
+    app: &Arc<AppContext>,
+    process_id: &str,
+    closed: &MtPosition<MtPositionClosedState>,
+    telemetry: &MyTelemetryContext,
+) {
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: None,
+                close_position: Some(map_closed_to_sb(closed)),
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+}
+
+async fn enqueue_update(
+    app: &Arc<AppContext>,
+    process_id: &str,
+    position: &MtPosition<MtPositionActiveState>,
+    telemetry: &MyTelemetryContext,
+) {
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: Some(map_active_to_sb_model(position.clone())),
+                close_position: None,
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use service_sdk::my_telemetry::MyTelemetryContext;
+
+    use crate::{
+        open_position,
+        position_manager_grpc::PositionManagerOpenPositionGrpcRequest,
+        test_utils::{create_test_active_position, create_test_app, create_test_bid_ask},
+        EngineError, TrailingStop,
+    };
+
+    use super::{AccountMode, AccountModes};
+
+    fn create_open_request(
+        side: i32,
+        invest_amount: f64,
+    ) -> PositionManagerOpenPositionGrpcRequest {
+        PositionManagerOpenPositionGrpcRequest {
+            asset_pair: "EURUSD".to_string(),
+            side,
+            invest_amount,
+            leverage: 10.0,
+            stop_out_percent: 90.0,
+            process_id: "netting-process".to_string(),
+            tp_in_profit: None,
+            sl_in_profit: None,
+            tp_in_asset_price: None,
+            sl_in_asset_price: None,
+            open_price: None,
+            open_bid_ask: None,
+            account_id: "account".to_string(),
+            trader_id: "trader".to_string(),
+            base: "EUR".to_string(),
+            quote: "USD".to_string(),
+            collateral_currency: "USD".to_string(),
+            id: Some("new".to_string()),
+            open_process_id: None,
+            metadata: HashMap::new(),
+            topping_up_percent: None,
+            margin_call_percent: None,
+            trailing_stop_distance: None,
+            trailing_stop_percent: None,
+            break_even_profit: None,
+            break_even_percent: None,
+            break_even_offset: None,
+            max_slippage: None,
+            max_slippage_points: None,
+            point_size: None,
+        }
+    }
+
+    #[test]
+    fn test_account_mode_resolution() {
+        let modes = AccountModes::new(
+            &["netting-account".to_string()],
+            &["netting".to_string()],
+            HashMap::from([
+                ("account".to_string(), "netting".to_string()),
+                ("hedging-account".to_string(), "hedging".to_string()),
+            ]),
+        );
+
+        assert_eq!(modes.get_mode("netting-account"), AccountMode::Netting);
+        assert_eq!(modes.get_mode("account"), AccountMode::Netting);
+        assert_eq!(modes.get_mode("hedging-account"), AccountMode::Hedging);
+        assert_eq!(modes.get_mode("unknown-account"), AccountMode::Hedging);
+    }
+
+    #[tokio::test]
+    async fn test_same_side_open_applies_protection() {
+        let mut app = create_test_app();
+        Arc::get_mut(&mut app).unwrap().account_modes =
+            AccountModes::new(&["account".to_string()], &[], HashMap::new());
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.1, 1.1));
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position("existing", "trader", "account"));
+
+        let mut request = create_open_request(0, 50.0);
+        request.sl_in_asset_price = Some(1.0);
+        request.trailing_stop_distance = Some(0.01);
+
+        let result = open_position(&app, request, &MyTelemetryContext::new())
+            .await
+            .unwrap();
+        let position = result.position.unwrap();
+
+        assert_eq!(position.base_data.id, "existing");
+        assert_eq!(position.base_data.invest_amount, 150.0);
+        assert_eq!(position.base_data.sl_price, Some(1.0));
+        assert_eq!(
+            TrailingStop::from_metadata(position.base_data.metadata.as_ref()),
+            Some(TrailingStop::Distance(0.01))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opposite_open_reduces_and_reverses() {
+        let mut app = create_test_app();
+        Arc::get_mut(&mut app).unwrap().account_modes =
+            AccountModes::new(&["account".to_string()], &[], HashMap::new());
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.1, 1.1));
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position("existing", "trader", "account"));
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+
+        let reduced = open_position(&app, create_open_request(1, 40.0), &telemetry)
+            .await
+            .unwrap();
+        let position = reduced.position.unwrap();
+        assert_eq!(position.base_data.id, "existing");
+        assert_eq!(position.base_data.invest_amount, 60.0);
+        assert_eq!(reduced.closed_positions.len(), 1);
+
+        let reversed = open_position(&app, create_open_request(1, 100.0), &telemetry)
+            .await
+            .unwrap();
+        let position = reversed.position.unwrap();
+        assert_eq!(position.base_data.id, "new");
+        assert_eq!(position.base_data.invest_amount, 40.0);
+        assert_eq!(reversed.closed_positions[0].base_data.id, "existing");
+
+        let cache = app.active_positions_cache.read().await;
+        assert!(cache.0.get_by_id("existing").is_none());
+        assert!(cache.0.get_by_id("new").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_open_with_other_leverage_is_rejected() {
+        let mut app = create_test_app();
+        Arc::get_mut(&mut app).unwrap().account_modes =
+            AccountModes::new(&["account".to_string()], &[], HashMap::new());
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.1, 1.1));
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position("existing", "trader", "account"));
+
+        let mut request = create_open_request(1, 50.0);
+        request.leverage = 20.0;
+
+        let result = open_position(&app, request, &MyTelemetryContext::new()).await;
+        assert!(matches!(result, Err(EngineError::LeverageMismatch)));
+
+        let cache = app.active_positions_cache.read().await;
+        let existing = cache.0.get_by_id("existing").unwrap();
+        assert_eq!(existing.base_data.invest_amount, 100.0);
+        assert!(cache.0.get_by_id("new").is_none());
+        assert_eq!(app.persistence_outbox.len().await, 0);
+    }
+}
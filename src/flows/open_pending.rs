@@ -3,22 +3,23 @@ use std::sync::Arc;
 use cfd_engine_sb_contracts::PendingPositionPersistenceEvent;
 use service_sdk::my_telemetry::MyTelemetryContext;
 use trading_sdk::mt_engine::{
-    create_pending_position, MtEngineError, MtPosition, MtPositionOpenPendingCommand,
-    MtPositionPendingState, MtPositionSide,
+    create_pending_position, MtPosition, MtPositionOpenPendingCommand, MtPositionPendingState,
+    MtPositionSide,
 };
 use uuid::Uuid;
 
 use crate::{
-    map_pending_to_sb_model,
+    get_pending_expire_at, is_position_id_taken, map_pending_to_sb_model,
     position_manager_grpc::{PositionManagerOpenPendingGrpcRequest, PositionManagerPositionSide},
-    AppContext,
+    publish_position_updates, AppContext, BreakEvenStop, EngineError, OutboxMessage, PendingGroup,
+    PositionUpdate, TrailingStop, EXPIRE_AT_METADATA_KEY,
 };
 
 pub async fn open_pending(
     app: &Arc<AppContext>,
-    request: PositionManagerOpenPendingGrpcRequest,
+    mut request: PositionManagerOpenPendingGrpcRequest,
     telemetry: &MyTelemetryContext,
-) -> Result<MtPosition<MtPositionPendingState>, MtEngineError> {
+) -> Result<MtPosition<MtPositionPendingState>, EngineError> {
     let id = match &request.id {
         Some(src) => src.clone(),
         None => Uuid::new_v4().to_string(),
@@ -31,6 +32,30 @@ pub async fn open_pending(
 
     let reed = app.active_prices_cache.read().await;
 
+    TrailingStop::write_metadata(
+        TrailingStop::from_request(request.trailing_stop_distance, request.trailing_stop_percent),
+        &mut request.metadata,
+    );
+    BreakEvenStop::write_metadata(
+        BreakEvenStop::from_request(
+            request.break_even_profit,
+            request.break_even_percent,
+            request.break_even_offset,
+        ),
+        &mut request.metadata,
+    );
+
+    PendingGroup::write_metadata(
+        PendingGroup::from_request(request.group_id.clone(), request.group_role),
+        &mut request.metadata,
+    );
+
+    if let Some(expire_at) = request.expire_at_unix_timestamp_milis {
+        request
+            .metadata
+            .insert(EXPIRE_AT_METADATA_KEY.to_string(), expire_at.to_string());
+    }
+
     let pending_position_command = MtPositionOpenPendingCommand {
         id,
         trader_id: request.trader_id,
@@ -60,25 +85,47 @@ pub async fn open_pending(
         margin_call_percent: request.margin_call_percent,
     };
 
+    let active_cache = app.active_positions_cache.read().await;
+    let mut pending_cache = app.pending_positions_cache.write().await;
+
+    {
+        let pending_to_confirm_cache = app.pending_execute_to_confirm_positions.read().await;
+
+        if is_position_id_taken(
+            &active_cache,
+            &pending_cache,
+            &pending_to_confirm_cache,
+            &pending_position_command.id,
+        ) {
+            return Err(EngineError::PositionAlreadyExists);
+        }
+    }
+
     let position = create_pending_position(pending_position_command, &reed)?;
 
-    app.pending_positions_cache
-        .write()
-        .await
-        .0
-        .add_position(position.clone());
+    pending_cache.0.add_position(position.clone());
+
+    if let Some(expire_at) = get_pending_expire_at(&position.base_data) {
+        app.pending_expirations
+            .lock()
+            .await
+            .arm(&position.base_data.id, expire_at);
+    }
 
     let sb_event = PendingPositionPersistenceEvent {
         process_id: request.process_id.clone(),
         cancel: None,
+        cancel_reason: None,
         execute: None,
         create: Some(map_pending_to_sb_model(position.clone())),
+        update: None,
     };
 
-    app.pending_positions_persistence_publisher
-        .publish(&sb_event, Some(telemetry))
-        .await
-        .unwrap();
+    app.persistence_outbox
+        .enqueue(OutboxMessage::PendingPosition(sb_event), Some(telemetry))
+        .await;
+
+    publish_position_updates(app, &[PositionUpdate::PendingCreated(&position)]).await;
 
     return Ok(position);
 }
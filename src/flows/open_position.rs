@@ -1,39 +1,92 @@
 use std::sync::Arc;
 
 use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use serde::Serialize;
 use service_sdk::my_telemetry::MyTelemetryContext;
 use trading_sdk::mt_engine::{
-    make_active_position, MtEngineError, MtPosition, MtPositionActiveState, MtPositionOpenCommand,
+    make_active_position, MtBidAskCache, MtPosition, MtPositionActiveState, MtPositionClosedState,
+    MtPositionOpenCommand, MtPositionSide,
 };
 use uuid::Uuid;
 
 use crate::{
-    map_active_to_sb_model,
+    check_slippage, find_netting_position_id, get_client_open_price, is_position_id_taken,
+    map_active_to_sb_model, open_netting_position,
     position_manager_grpc::{PositionManagerOpenPositionGrpcRequest, PositionManagerPositionSide},
-    AppContext,
+    publish_position_updates, AccountMode, AppContext, BreakEvenStop, EngineError, MaxSlippage,
+    OutboxMessage, PositionUpdate, TrailingStop,
 };
 
-pub async fn open_position(
-    app: &Arc<AppContext>,
-    request: PositionManagerOpenPositionGrpcRequest,
-    telemetry: &MyTelemetryContext,
-) -> Result<MtPosition<MtPositionActiveState>, MtEngineError> {
-    let prices_cache = app.active_prices_cache.read().await;
+// In netting mode an open may reduce or close the existing position of the instrument, so
+// the resulting active position is optional and the closed legs are reported alongside.
+#[derive(Clone, Serialize)]
+pub struct OpenPositionResult {
+    pub position: Option<MtPosition<MtPositionActiveState>>,
+    pub closed_positions: Vec<MtPosition<MtPositionClosedState>>,
+}
 
-    let id = match &request.id {
-        Some(src) => src.clone(),
-        None => Uuid::new_v4().to_string(),
-    };
+// Price checks done before the open is filled, shared with the open preview.
+pub fn check_open_price(
+    app: &AppContext,
+    prices_cache: &MtBidAskCache,
+    request: &PositionManagerOpenPositionGrpcRequest,
+) -> Result<(), EngineError> {
+    let side: PositionManagerPositionSide = request.side();
 
-    let side: PositionManagerPositionSide =
-        PositionManagerPositionSide::try_from(request.side).unwrap();
+    app.quote_age_limits.check_price(
+        prices_cache,
+        &request.asset_pair,
+        &request.base,
+        &request.quote,
+        &request.collateral_currency,
+    )?;
 
-    let trader_id = request.trader_id.clone();
-    let account_id = request.account_id.clone();
-    let process_id = request.process_id.clone();
+    if let Some(max_slippage) = MaxSlippage::from_request(
+        request.max_slippage,
+        request.max_slippage_points,
+        request.point_size,
+    ) {
+        let bid_ask = prices_cache
+            .get_by_id(&request.asset_pair)
+            .ok_or(EngineError::NoLiquidity)?;
+        let side: MtPositionSide = side.into();
 
-    let open_command = MtPositionOpenCommand {
-        id: id.clone(),
+        // Validation requires the client price along with the slippage, without it the open is
+        // rejected rather than filled blindly.
+        let client_price = get_client_open_price(
+            request.open_price,
+            request.open_bid_ask.as_ref(),
+            &side,
+        )
+        .ok_or(EngineError::PriceChanged)?;
+
+        check_slippage(&max_slippage, client_price, &bid_ask, &side)?;
+    }
+
+    return Ok(());
+}
+
+pub fn make_open_command(
+    id: &str,
+    mut request: PositionManagerOpenPositionGrpcRequest,
+) -> MtPositionOpenCommand {
+    let side: PositionManagerPositionSide = request.side();
+
+    TrailingStop::write_metadata(
+        TrailingStop::from_request(request.trailing_stop_distance, request.trailing_stop_percent),
+        &mut request.metadata,
+    );
+    BreakEvenStop::write_metadata(
+        BreakEvenStop::from_request(
+            request.break_even_profit,
+            request.break_even_percent,
+            request.break_even_offset,
+        ),
+        &mut request.metadata,
+    );
+
+    return MtPositionOpenCommand {
+        id: id.to_string(),
         trader_id: request.trader_id,
         account_id: request.account_id,
         side: side.into(),
@@ -60,6 +113,83 @@ pub async fn open_position(
         },
         margin_call_percent: request.margin_call_percent,
     };
+}
+
+pub async fn open_position(
+    app: &Arc<AppContext>,
+    request: PositionManagerOpenPositionGrpcRequest,
+    telemetry: &MyTelemetryContext,
+) -> Result<OpenPositionResult, EngineError> {
+    let prices_cache = app.active_prices_cache.read().await;
+
+    let id = match &request.id {
+        Some(src) => src.clone(),
+        None => Uuid::new_v4().to_string(),
+    };
+
+    let trader_id = request.trader_id.clone();
+    let account_id = request.account_id.clone();
+    let process_id = request.process_id.clone();
+    let account_mode = app.account_modes.get_mode(&request.account_id);
+
+    check_open_price(app, &prices_cache, &request)?;
+
+    let open_command = make_open_command(&id, request);
+
+    let mut positions_cache = app.active_positions_cache.write().await;
+
+    {
+        let pending_cache = app.pending_positions_cache.read().await;
+        let pending_to_confirm_cache = app.pending_execute_to_confirm_positions.read().await;
+
+        if is_position_id_taken(
+            &positions_cache,
+            &pending_cache,
+            &pending_to_confirm_cache,
+            &id,
+        ) {
+            return Err(EngineError::PositionAlreadyExists);
+        }
+    }
+
+    if account_mode == AccountMode::Netting {
+        if let Some(existing_position_id) = find_netting_position_id(
+            &positions_cache,
+            &open_command.trader_id,
+            &open_command.account_id,
+            &open_command.asset_pair,
+        ) {
+            let result = open_netting_position(
+                app,
+                &mut positions_cache,
+                &prices_cache,
+                &existing_position_id,
+                open_command,
+                telemetry,
+            )
+            .await;
+
+            if let Ok(result) = &result {
+                let mut updates: Vec<_> = result
+                    .closed_positions
+                    .iter()
+                    .map(PositionUpdate::Closed)
+                    .collect();
+                // A reversal closes the existing position and opens a new one under the
+                // requested id.
+                updates.extend(result.position.iter().map(|position| {
+                    if position.base_data.id == existing_position_id {
+                        PositionUpdate::Updated(position)
+                    } else {
+                        PositionUpdate::Opened(position)
+                    }
+                }));
+                publish_position_updates(app, &updates).await;
+            }
+
+            return result;
+        }
+    }
 
     let position = make_active_position(open_command.clone(), &prices_cache)?;
 
@@ -74,21 +204,23 @@ pub async fn open_position(
         "active_position" = &position
     );
 
-    let mut positions_cache = app.active_positions_cache.write().await;
-
     positions_cache.0.add_position(position.clone());
 
     let sb_model = PositionPersistenceEvent {
-        process_id: request.process_id,
+        process_id,
         update_position: None,
         close_position: None,
         create_position: Some(map_active_to_sb_model(position.clone())),
     };
 
-    app.active_positions_persistence_publisher
-        .publish(&sb_model, Some(telemetry))
-        .await
-        .unwrap();
+    app.persistence_outbox
+        .enqueue(OutboxMessage::ActivePosition(sb_model), Some(telemetry))
+        .await;
+
+    publish_position_updates(app, &[PositionUpdate::Opened(&position)]).await;
 
-    return Ok(position);
+    return Ok(OpenPositionResult {
+        position: Some(position),
+        closed_positions: vec![],
+    });
 }
@@ -0,0 +1,305 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use service_sdk::{
+    my_telemetry::MyTelemetryContext, rust_extensions::date_time::DateTimeAsMicroseconds,
+};
+use trading_sdk::mt_engine::{
+    convert_position_to_closed, MtPosition, MtPositionActiveState, MtPositionCloseReason,
+    MtPositionClosedState, MtPositionSwap, MtPositionSwaps,
+};
+use uuid::Uuid;
+
+use crate::{
+    apply_closed_position_profit, map_active_to_sb_model, map_closed_to_sb,
+    publish_position_updates, AppContext, EngineError, OutboxMessage, PositionUpdate,
+};
+
+pub const PARENT_POSITION_ID_METADATA_KEY: &str = "ParentPositionId";
+
+pub enum PartialCloseVolume {
+    InvestAmount(f64),
+    Percent(f64),
+}
+
+impl PartialCloseVolume {
+    pub fn get_invest_amount(&self, position_invest_amount: f64) -> f64 {
+        match self {
+            PartialCloseVolume::InvestAmount(amount) => *amount,
+            PartialCloseVolume::Percent(percent) => position_invest_amount * percent / 100.0,
+        }
+    }
+}
+
+pub async fn partial_close_position(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+    position_id: &str,
+    volume: PartialCloseVolume,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) -> Result<
+    (
+        MtPosition<MtPositionClosedState>,
+        MtPosition<MtPositionActiveState>,
+    ),
+    EngineError,
+> {
+    let mut cache = app.active_positions_cache.write().await;
+
+    let invest_amount = cache
+        .0
+        .get_by_id(position_id)
+        .ok_or(EngineError::PositionNotFound)?
+        .base_data
+        .invest_amount;
+
+    let close_amount = volume.get_invest_amount(invest_amount);
+
+    if !close_amount.is_finite() || close_amount <= 0.0 || close_amount >= invest_amount {
+        return Err(EngineError::InvalidAmount);
+    }
+
+    let active_position = cache
+        .0
+        .remove_position(position_id)
+        .ok_or(EngineError::PositionNotFound)?;
+
+    let (closed_part, remaining) =
+        split_active_position(active_position.clone(), close_amount, process_id);
+
+    let closed = convert_position_to_closed(
+        closed_part.clone(),
+        MtPositionCloseReason::ClientCommand,
+        process_id.to_string(),
+    );
+
+    cache.0.add_position(remaining.clone());
+
+    trade_log::trade_log!(
+        trader_id,
+        account_id,
+        process_id,
+        position_id,
+        "Executing partial close position",
+        telemetry.clone(),
+        "active_position" = &active_position,
+        "closed_part" = &closed,
+        "remaining_position" = &remaining
+    );
+
+    enqueue_split_part_create(app, process_id, &closed_part, telemetry).await;
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: None,
+                close_position: Some(map_closed_to_sb(&closed)),
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    apply_closed_position_profit(app, &closed).await;
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: Some(map_active_to_sb_model(remaining.clone())),
+                close_position: None,
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    publish_position_updates(
+        app,
+        &[
+            PositionUpdate::Closed(&closed),
+            PositionUpdate::Updated(&remaining),
+        ],
+    )
+    .await;
+
+    return Ok((closed, remaining));
+}
+
+// The split-off part gets a new id, so persistence has to see it created before it is closed.
+pub async fn enqueue_split_part_create(
+    app: &AppContext,
+    process_id: &str,
+    split_part: &MtPosition<MtPositionActiveState>,
+    telemetry: &MyTelemetryContext,
+) {
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: None,
+                close_position: None,
+                create_position: Some(map_active_to_sb_model(split_part.clone())),
+            }),
+            Some(telemetry),
+        )
+        .await;
+}
+
+// Splits the position in two by invest amount. Profit, swaps and reserved topping up funds
+// are pro-rated; profit based SL/TP of the remaining part are scaled so that they still
+// trigger at the same asset price.
+pub fn split_active_position(
+    position: MtPosition<MtPositionActiveState>,
+    close_amount: f64,
+    process_id: &str,
+) -> (
+    MtPosition<MtPositionActiveState>,
+    MtPosition<MtPositionActiveState>,
+) {
+    let close_ratio = close_amount / position.base_data.invest_amount;
+    let remaining_ratio = 1.0 - close_ratio;
+    let now = DateTimeAsMicroseconds::now();
+
+    let mut closed_part = position.clone();
+    closed_part.base_data.id = Uuid::new_v4().to_string();
+    closed_part.base_data.invest_amount = close_amount;
+    closed_part.base_data.last_update_date = now;
+    closed_part.base_data.last_update_process_id = process_id.to_string();
+    closed_part
+        .base_data
+        .metadata
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            PARENT_POSITION_ID_METADATA_KEY.to_string(),
+            position.base_data.id.clone(),
+        );
+    scale_active_state(&mut closed_part.state, close_ratio);
+
+    let mut remaining = position;
+    remaining.base_data.invest_amount -= close_amount;
+    remaining.base_data.sl_profit = remaining.base_data.sl_profit.map(|x| x * remaining_ratio);
+    remaining.base_data.tp_profit = remaining.base_data.tp_profit.map(|x| x * remaining_ratio);
+    remaining.base_data.last_update_date = now;
+    remaining.base_data.last_update_process_id = process_id.to_string();
+    scale_active_state(&mut remaining.state, remaining_ratio);
+
+    return (closed_part, remaining);
+}
+
+pub fn scale_active_state(state: &mut MtPositionActiveState, ratio: f64) {
+    state.profit *= ratio;
+    state.topping_up = state.topping_up.map(|x| x * ratio);
+    state.swaps = MtPositionSwaps {
+        swaps: state
+            .swaps
+            .swaps
+            .iter()
+            .map(|x| MtPositionSwap {
+                date: x.date,
+                amount: x.amount * ratio,
+            })
+            .collect(),
+        total: state.swaps.total * ratio,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use service_sdk::my_telemetry::MyTelemetryContext;
+    use trading_sdk::mt_engine::MtPositionSwap;
+
+    use crate::{
+        test_utils::{create_test_active_position, create_test_app},
+        OutboxMessage,
+    };
+
+    use super::{
+        partial_close_position, split_active_position, PartialCloseVolume,
+        PARENT_POSITION_ID_METADATA_KEY,
+    };
+
+    #[test]
+    fn test_split_pro_rates_position() {
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.state.profit = 20.0;
+        position.state.topping_up = Some(40.0);
+        position.state.swaps.swaps.push(MtPositionSwap {
+            date: position.base_data.crate_date,
+            amount: -8.0,
+        });
+        position.state.swaps.total = -8.0;
+        position.base_data.sl_profit = Some(-50.0);
+
+        let (closed_part, remaining) = split_active_position(position, 25.0, "process");
+
+        assert_ne!(closed_part.base_data.id, "id");
+        assert_eq!(
+            closed_part
+                .base_data
+                .metadata
+                .as_ref()
+                .unwrap()
+                .get(PARENT_POSITION_ID_METADATA_KEY)
+                .unwrap(),
+            "id"
+        );
+        assert_eq!(closed_part.base_data.invest_amount, 25.0);
+        assert_eq!(closed_part.state.profit, 5.0);
+        assert_eq!(closed_part.state.topping_up, Some(10.0));
+        assert_eq!(closed_part.state.swaps.total, -2.0);
+
+        assert_eq!(remaining.base_data.id, "id");
+        assert_eq!(remaining.base_data.invest_amount, 75.0);
+        assert_eq!(remaining.state.profit, 15.0);
+        assert_eq!(remaining.state.topping_up, Some(30.0));
+        assert_eq!(remaining.state.swaps.swaps[0].amount, -6.0);
+        assert_eq!(remaining.base_data.sl_profit, Some(-37.5));
+        assert_eq!(remaining.base_data.last_update_process_id, "process");
+    }
+
+    #[tokio::test]
+    async fn test_partial_close_creates_split_part() {
+        let app = create_test_app();
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position("id", "trader", "account"));
+
+        let (closed, _) = partial_close_position(
+            &app,
+            "trader",
+            "account",
+            "id",
+            PartialCloseVolume::Percent(40.0),
+            "process",
+            &MyTelemetryContext::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut events = vec![];
+        while let Some(item) = app.persistence_outbox.peek().await {
+            app.persistence_outbox.ack(item.id).await;
+            match item.message {
+                OutboxMessage::ActivePosition(event) => events.push(event),
+                _ => panic!("Unexpected outbox message"),
+            }
+        }
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0].create_position.as_ref().unwrap().id,
+            closed.base_data.id
+        );
+        assert_eq!(
+            events[1].close_position.as_ref().unwrap().id,
+            closed.base_data.id
+        );
+        assert_eq!(events[2].update_position.as_ref().unwrap().id, "id");
+    }
+}
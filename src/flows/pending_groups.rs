@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+
+use trading_sdk::{
+    core::EngineCacheQueryBuilder,
+    mt_engine::{
+        sanitize_sl_tp, MtPosition, MtPositionActiveState, MtPositionBaseData,
+        MtPositionPendingState, PendingPositionsCache,
+    },
+};
+
+use crate::position_manager_grpc::PositionManagerPendingGroupRole;
+
+pub const PENDING_GROUP_ID_METADATA_KEY: &str = "PendingGroupId";
+pub const PENDING_GROUP_ROLE_METADATA_KEY: &str = "PendingGroupRole";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PendingGroupRole {
+    Oco,
+    BracketEntry,
+    BracketTakeProfit,
+    BracketStopLoss,
+}
+
+impl PendingGroupRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PendingGroupRole::Oco => "Oco",
+            PendingGroupRole::BracketEntry => "BracketEntry",
+            PendingGroupRole::BracketTakeProfit => "BracketTakeProfit",
+            PendingGroupRole::BracketStopLoss => "BracketStopLoss",
+        }
+    }
+
+    pub fn parse(src: &str) -> Option<Self> {
+        match src {
+            "Oco" => Some(PendingGroupRole::Oco),
+            "BracketEntry" => Some(PendingGroupRole::BracketEntry),
+            "BracketTakeProfit" => Some(PendingGroupRole::BracketTakeProfit),
+            "BracketStopLoss" => Some(PendingGroupRole::BracketStopLoss),
+            _ => None,
+        }
+    }
+
+    // Take-profit and stop-loss legs of a bracket can not execute until the entry is filled.
+    pub fn is_dormant(&self) -> bool {
+        matches!(
+            self,
+            PendingGroupRole::BracketTakeProfit | PendingGroupRole::BracketStopLoss
+        )
+    }
+}
+
+impl From<PositionManagerPendingGroupRole> for PendingGroupRole {
+    fn from(src: PositionManagerPendingGroupRole) -> Self {
+        match src {
+            PositionManagerPendingGroupRole::Oco => PendingGroupRole::Oco,
+            PositionManagerPendingGroupRole::BracketEntry => PendingGroupRole::BracketEntry,
+            PositionManagerPendingGroupRole::BracketTakeProfit => {
+                PendingGroupRole::BracketTakeProfit
+            }
+            PositionManagerPendingGroupRole::BracketStopLoss => PendingGroupRole::BracketStopLoss,
+        }
+    }
+}
+
+// Groups are kept in the order metadata, like the other settings the engine has no place for.
+// Orders of a group belong to the same trader and account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingGroup {
+    pub id: String,
+    pub role: PendingGroupRole,
+}
+
+impl PendingGroup {
+    pub fn from_request(group_id: Option<String>, group_role: Option<i32>) -> Option<Self> {
+        let role = PositionManagerPendingGroupRole::try_from(group_role?).ok()?;
+
+        return Some(Self {
+            id: group_id?,
+            role: role.into(),
+        });
+    }
+
+    pub fn from_metadata(metadata: Option<&HashMap<String, String>>) -> Option<Self> {
+        let metadata = metadata?;
+
+        return Some(Self {
+            id: metadata.get(PENDING_GROUP_ID_METADATA_KEY)?.clone(),
+            role: PendingGroupRole::parse(metadata.get(PENDING_GROUP_ROLE_METADATA_KEY)?)?,
+        });
+    }
+
+    pub fn write_metadata(group: Option<Self>, metadata: &mut HashMap<String, String>) {
+        metadata.remove(PENDING_GROUP_ID_METADATA_KEY);
+        metadata.remove(PENDING_GROUP_ROLE_METADATA_KEY);
+
+        if let Some(group) = group {
+            metadata.insert(PENDING_GROUP_ID_METADATA_KEY.to_string(), group.id);
+            metadata.insert(
+                PENDING_GROUP_ROLE_METADATA_KEY.to_string(),
+                group.role.as_str().to_string(),
+            );
+        }
+    }
+}
+
+pub fn is_pending_group_dormant(base_data: &MtPositionBaseData) -> bool {
+    return PendingGroup::from_metadata(base_data.metadata.as_ref())
+        .map(|x| x.role.is_dormant())
+        .unwrap_or(false);
+}
+
+fn get_group_member_ids(
+    cache: &PendingPositionsCache,
+    base_data: &MtPositionBaseData,
+    group_id: &str,
+    role: PendingGroupRole,
+) -> Vec<String> {
+    let query = EngineCacheQueryBuilder::new()
+        .with_client(&base_data.trader_id)
+        .with_account(&base_data.account_id);
+
+    return cache
+        .0
+        .query_positions(query)
+        .into_iter()
+        .filter(|x| {
+            PendingGroup::from_metadata(x.base_data.metadata.as_ref())
+                .map(|group| group.id == group_id && group.role == role)
+                .unwrap_or(false)
+        })
+        .map(|x| x.base_data.id.clone())
+        .collect();
+}
+
+// Only one order of an OCO group may execute. The rest of the group is removed from the cache
+// under the same lock and returned as cancelled, including siblings selected on the same tick
+// and the exit legs of cancelled bracket entries.
+pub fn resolve_oco_groups(
+    cache: &mut PendingPositionsCache,
+    selected: Vec<MtPosition<MtPositionPendingState>>,
+) -> (
+    Vec<MtPosition<MtPositionPendingState>>,
+    Vec<MtPosition<MtPositionPendingState>>,
+) {
+    let mut to_execute = vec![];
+    let mut cancelled = vec![];
+    let mut executed_groups = HashSet::new();
+
+    for position in selected {
+        let group = match PendingGroup::from_metadata(position.base_data.metadata.as_ref()) {
+            Some(group) if group.role == PendingGroupRole::Oco => group,
+            _ => {
+                to_execute.push(position);
+                continue;
+            }
+        };
+
+        if !executed_groups.insert(group.id.clone()) {
+            cancelled.push(position);
+            continue;
+        }
+
+        for id in get_group_member_ids(cache, &position.base_data, &group.id, group.role) {
+            if let Some(sibling) = cache.0.remove_position(&id) {
+                cancelled.push(sibling);
+            }
+        }
+
+        to_execute.push(position);
+    }
+
+    let exits: Vec<_> = cancelled
+        .iter()
+        .flat_map(|x| take_bracket_exits(cache, &x.base_data))
+        .collect();
+    cancelled.extend(exits);
+
+    return (to_execute, cancelled);
+}
+
+// The exit legs of a bracket live only as long as its entry. They are removed from the cache
+// when the entry is filled, cancelled, expired or rejected.
+pub fn take_bracket_exits(
+    cache: &mut PendingPositionsCache,
+    entry: &MtPositionBaseData,
+) -> Vec<MtPosition<MtPositionPendingState>> {
+    let group = match PendingGroup::from_metadata(entry.metadata.as_ref()) {
+        Some(group) if group.role == PendingGroupRole::BracketEntry => group,
+        _ => return vec![],
+    };
+
+    let mut ids =
+        get_group_member_ids(cache, entry, &group.id, PendingGroupRole::BracketTakeProfit);
+    ids.extend(get_group_member_ids(
+        cache,
+        entry,
+        &group.id,
+        PendingGroupRole::BracketStopLoss,
+    ));
+
+    return ids
+        .iter()
+        .filter_map(|id| cache.0.remove_position(id))
+        .collect();
+}
+
+// The position filled from the entry takes the desire prices of the exit legs as its take
+// profit and stop loss, so the exits are closed together with the position.
+pub fn apply_bracket_exits(
+    position: &mut MtPosition<MtPositionActiveState>,
+    exits: &[MtPosition<MtPositionPendingState>],
+) {
+    for exit in exits {
+        let role = PendingGroup::from_metadata(exit.base_data.metadata.as_ref()).map(|x| x.role);
+
+        match role {
+            Some(PendingGroupRole::BracketTakeProfit) => {
+                position.base_data.tp_price = Some(exit.state.desire_price);
+                position.base_data.tp_profit = None;
+            }
+            Some(PendingGroupRole::BracketStopLoss) => {
+                position.base_data.sl_price = Some(exit.state.desire_price);
+                position.base_data.sl_profit = None;
+            }
+            _ => {}
+        }
+    }
+
+    sanitize_sl_tp(&mut position.base_data);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use trading_sdk::mt_engine::{MtPosition, MtPositionPendingState, PendingPositionsCache};
+
+    use crate::test_utils::{create_test_active_position, create_test_pending_position};
+
+    use super::{
+        apply_bracket_exits, is_pending_group_dormant, resolve_oco_groups, take_bracket_exits,
+        PendingGroup, PendingGroupRole,
+    };
+
+    fn create_group_member(
+        id: &str,
+        group_id: &str,
+        role: PendingGroupRole,
+    ) -> MtPosition<MtPositionPendingState> {
+        let mut position = create_test_pending_position(id, "trader", "account");
+        let mut metadata = HashMap::new();
+        PendingGroup::write_metadata(
+            Some(PendingGroup {
+                id: group_id.to_string(),
+                role,
+            }),
+            &mut metadata,
+        );
+        position.base_data.metadata = Some(metadata);
+        return position;
+    }
+
+    #[test]
+    fn test_oco_cancels_siblings() {
+        let mut cache = PendingPositionsCache::new();
+        cache.0.add_position(create_group_member(
+            "second",
+            "group",
+            PendingGroupRole::Oco,
+        ));
+        cache
+            .0
+            .add_position(create_group_member("other", "other", PendingGroupRole::Oco));
+
+        let selected = vec![
+            create_group_member("first", "group", PendingGroupRole::Oco),
+            create_group_member("third", "group", PendingGroupRole::Oco),
+        ];
+
+        let (to_execute, cancelled) = resolve_oco_groups(&mut cache, selected);
+
+        assert_eq!(to_execute.len(), 1);
+        assert_eq!(to_execute[0].base_data.id, "first");
+
+        let mut cancelled_ids: Vec<_> = cancelled.iter().map(|x| x.base_data.id.clone()).collect();
+        cancelled_ids.sort();
+        assert_eq!(
+            cancelled_ids,
+            vec!["second".to_string(), "third".to_string()]
+        );
+
+        assert!(cache.0.get_by_id("second").is_none());
+        assert!(cache.0.get_by_id("other").is_some());
+    }
+
+    #[test]
+    fn test_bracket_exits_taken_by_entry() {
+        let mut cache = PendingPositionsCache::new();
+
+        let mut tp = create_group_member("tp", "bracket", PendingGroupRole::BracketTakeProfit);
+        tp.state.desire_price = 1.2;
+        cache.0.add_position(tp);
+
+        let mut sl = create_group_member("sl", "bracket", PendingGroupRole::BracketStopLoss);
+        sl.state.desire_price = 1.0;
+        cache.0.add_position(sl);
+
+        cache
+            .0
+            .add_position(create_group_member("other", "other", PendingGroupRole::Oco));
+
+        assert!(is_pending_group_dormant(
+            &cache.0.get_by_id("tp").unwrap().base_data
+        ));
+
+        let entry = create_group_member("entry", "bracket", PendingGroupRole::BracketEntry);
+        let exits = take_bracket_exits(&mut cache, &entry.base_data);
+
+        assert_eq!(exits.len(), 2);
+        assert!(cache.0.get_by_id("tp").is_none());
+        assert!(cache.0.get_by_id("sl").is_none());
+        assert!(cache.0.get_by_id("other").is_some());
+
+        let mut position = create_test_active_position("entry", "trader", "account");
+        apply_bracket_exits(&mut position, &exits);
+
+        assert_eq!(position.base_data.tp_price, Some(1.2));
+        assert_eq!(position.base_data.sl_price, Some(1.0));
+    }
+}
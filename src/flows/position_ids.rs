@@ -0,0 +1,13 @@
+use trading_sdk::mt_engine::{ActivePositionsCache, PendingPositionsCache};
+
+// Callers lock the caches in the order active -> pending -> awaiting confirmation.
+pub fn is_position_id_taken(
+    active_cache: &ActivePositionsCache,
+    pending_cache: &PendingPositionsCache,
+    pending_to_confirm_cache: &PendingPositionsCache,
+    id: &str,
+) -> bool {
+    return active_cache.0.get_by_id(id).is_some()
+        || pending_cache.0.get_by_id(id).is_some()
+        || pending_to_confirm_cache.0.get_by_id(id).is_some();
+}
\ No newline at end of file
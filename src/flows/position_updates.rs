@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use trading_sdk::{
+    core::EngineCacheQueryBuilder,
+    mt_engine::{MtPosition, MtPositionActiveState, MtPositionClosedState, MtPositionPendingState},
+};
+
+use crate::{
+    position_manager_grpc::{
+        PositionManagerPositionUpdateGrpcModel, PositionManagerPositionUpdateType,
+    },
+    AppContext,
+};
+
+const DEFAULT_PROFIT_UPDATE_INTERVAL_MS: u64 = 1000;
+const MIN_PROFIT_UPDATE_INTERVAL_MS: u64 = 100;
+
+pub fn get_profit_update_interval(interval_ms: Option<u64>) -> Duration {
+    return Duration::from_millis(
+        interval_ms
+            .unwrap_or(DEFAULT_PROFIT_UPDATE_INTERVAL_MS)
+            .max(MIN_PROFIT_UPDATE_INTERVAL_MS),
+    );
+}
+
+pub enum PositionUpdate<'a> {
+    Opened(&'a MtPosition<MtPositionActiveState>),
+    Updated(&'a MtPosition<MtPositionActiveState>),
+    ProfitChanged(&'a MtPosition<MtPositionActiveState>),
+    SlTpChanged(&'a MtPosition<MtPositionActiveState>),
+    MarginCall(&'a MtPosition<MtPositionActiveState>),
+    PendingExecuted(&'a MtPosition<MtPositionActiveState>),
+    Closed(&'a MtPosition<MtPositionClosedState>),
+    PendingCreated(&'a MtPosition<MtPositionPendingState>),
+    PendingModified(&'a MtPosition<MtPositionPendingState>),
+    PendingCancelled(&'a MtPosition<MtPositionPendingState>),
+}
+
+impl<'a> PositionUpdate<'a> {
+    fn get_owner(&self) -> (&str, &str, &str) {
+        let base_data = match self {
+            PositionUpdate::Opened(x)
+            | PositionUpdate::Updated(x)
+            | PositionUpdate::ProfitChanged(x)
+            | PositionUpdate::SlTpChanged(x)
+            | PositionUpdate::MarginCall(x)
+            | PositionUpdate::PendingExecuted(x) => &x.base_data,
+            PositionUpdate::Closed(x) => &x.base_data,
+            PositionUpdate::PendingCreated(x)
+            | PositionUpdate::PendingModified(x)
+            | PositionUpdate::PendingCancelled(x) => &x.base_data,
+        };
+
+        return (&base_data.trader_id, &base_data.account_id, &base_data.id);
+    }
+
+    fn to_grpc(&self) -> PositionManagerPositionUpdateGrpcModel {
+        let (update_type, active_position) = match self {
+            PositionUpdate::Opened(x) => (PositionManagerPositionUpdateType::Opened, *x),
+            PositionUpdate::Updated(x) => (PositionManagerPositionUpdateType::Updated, *x),
+            PositionUpdate::ProfitChanged(x) => {
+                (PositionManagerPositionUpdateType::ProfitChanged, *x)
+            }
+            PositionUpdate::SlTpChanged(x) => (PositionManagerPositionUpdateType::SlTpChanged, *x),
+            PositionUpdate::MarginCall(x) => (PositionManagerPositionUpdateType::MarginCall, *x),
+            PositionUpdate::PendingExecuted(x) => {
+                (PositionManagerPositionUpdateType::PendingExecuted, *x)
+            }
+            PositionUpdate::Closed(x) => {
+                return PositionManagerPositionUpdateGrpcModel {
+                    r#type: PositionManagerPositionUpdateType::Closed as i32,
+                    active_position: None,
+                    pending_position: None,
+                    closed_position: Some((*x).clone().into()),
+                };
+            }
+            PositionUpdate::PendingCreated(x) => {
+                return map_pending_update(PositionManagerPositionUpdateType::PendingCreated, x);
+            }
+            PositionUpdate::PendingModified(x) => {
+                return map_pending_update(PositionManagerPositionUpdateType::PendingModified, x);
+            }
+            PositionUpdate::PendingCancelled(x) => {
+                return map_pending_update(PositionManagerPositionUpdateType::PendingCancelled, x);
+            }
+        };
+
+        return PositionManagerPositionUpdateGrpcModel {
+            r#type: update_type as i32,
+            active_position: Some(active_position.clone().into()),
+            pending_position: None,
+            closed_position: None,
+        };
+    }
+}
+
+fn map_pending_update(
+    update_type: PositionManagerPositionUpdateType,
+    pending_position: &MtPosition<MtPositionPendingState>,
+) -> PositionManagerPositionUpdateGrpcModel {
+    return PositionManagerPositionUpdateGrpcModel {
+        r#type: update_type as i32,
+        active_position: None,
+        pending_position: Some(pending_position.clone().into()),
+        closed_position: None,
+    };
+}
+
+// Positions are only mapped for accounts somebody is subscribed to, the price loop calls this
+// for every updated position.
+pub async fn publish_position_updates(app: &AppContext, updates: &[PositionUpdate<'_>]) {
+    let mut hub = app.position_updates_hub.lock().await;
+
+    for update in updates {
+        let (trader_id, account_id, position_id) = update.get_owner();
+
+        if !hub.has_subscribers(trader_id, account_id) {
+            continue;
+        }
+
+        hub.publish(trader_id, account_id, position_id, update.to_grpc());
+    }
+}
+
+pub async fn subscribe_account_positions(
+    app: &AppContext,
+    trader_id: &str,
+    account_id: &str,
+    profit_update_interval: Duration,
+) -> tokio::sync::mpsc::Receiver<PositionManagerPositionUpdateGrpcModel> {
+    let active_cache = app.active_positions_cache.read().await;
+    let pending_cache = app.pending_positions_cache.read().await;
+
+    let mut snapshot: Vec<PositionManagerPositionUpdateGrpcModel> = active_cache
+        .0
+        .query_positions(
+            EngineCacheQueryBuilder::new()
+                .with_client(trader_id)
+                .with_account(account_id),
+        )
+        .into_iter()
+        .map(|x| PositionManagerPositionUpdateGrpcModel {
+            r#type: PositionManagerPositionUpdateType::Snapshot as i32,
+            active_position: Some(x.clone().into()),
+            pending_position: None,
+            closed_position: None,
+        })
+        .collect();
+
+    snapshot.extend(
+        pending_cache
+            .0
+            .query_positions(
+                EngineCacheQueryBuilder::new()
+                    .with_client(trader_id)
+                    .with_account(account_id),
+            )
+            .into_iter()
+            .map(|x| PositionManagerPositionUpdateGrpcModel {
+                r#type: PositionManagerPositionUpdateType::Snapshot as i32,
+                active_position: None,
+                pending_position: Some(x.clone().into()),
+                closed_position: None,
+            }),
+    );
+
+    snapshot.push(PositionManagerPositionUpdateGrpcModel {
+        r#type: PositionManagerPositionUpdateType::SnapshotCompleted as i32,
+        active_position: None,
+        pending_position: None,
+        closed_position: None,
+    });
+
+    // The caches stay locked until the subscriber is registered, updates published by the
+    // writers can not slip in between the snapshot and the subscription.
+    return app.position_updates_hub.lock().await.subscribe(
+        trader_id,
+        account_id,
+        profit_update_interval,
+        snapshot,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        cancel_pending,
+        position_manager_grpc::{
+            PositionManagerCancelPendingGrpcRequest, PositionManagerPositionUpdateType,
+        },
+        test_utils::{create_test_app, create_test_pending_position},
+    };
+
+    use super::subscribe_account_positions;
+
+    #[tokio::test]
+    async fn test_cancelled_pending_is_published() {
+        let app = create_test_app();
+
+        app.pending_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_pending_position("pending", "trader", "account"));
+
+        let mut receiver =
+            subscribe_account_positions(&app, "trader", "account", Duration::from_secs(1)).await;
+
+        cancel_pending(
+            &app,
+            PositionManagerCancelPendingGrpcRequest {
+                id: "pending".to_string(),
+                trader_id: "trader".to_string(),
+                account_id: "account".to_string(),
+            },
+            &service_sdk::my_telemetry::MyTelemetryContext::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut types = vec![];
+        while let Ok(update) = receiver.try_recv() {
+            types.push(update.r#type);
+        }
+
+        assert_eq!(
+            types,
+            vec![
+                PositionManagerPositionUpdateType::Snapshot as i32,
+                PositionManagerPositionUpdateType::SnapshotCompleted as i32,
+                PositionManagerPositionUpdateType::PendingCancelled as i32,
+            ]
+        );
+    }
+}
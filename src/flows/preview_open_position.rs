@@ -0,0 +1,111 @@
+use serde::Serialize;
+use trading_sdk::mt_engine::{
+    make_active_position, sanitize_sl_tp, MtPosition, MtPositionActiveState,
+};
+
+use crate::{
+    calculate_trigger_prices, check_open_price, make_open_command,
+    position_manager_grpc::PositionManagerOpenPositionGrpcRequest, AppContext, EngineError,
+    PositionTriggerPrices,
+};
+
+// The position is built the same way an open fills it, but it is never added to the cache nor
+// persisted. In netting mode the preview shows the open as a standalone position.
+#[derive(Clone, Serialize)]
+pub struct OpenPositionPreview {
+    pub position: MtPosition<MtPositionActiveState>,
+    pub required_margin: f64,
+    pub trigger_prices: PositionTriggerPrices,
+}
+
+pub async fn preview_open_position(
+    app: &AppContext,
+    request: PositionManagerOpenPositionGrpcRequest,
+) -> Result<OpenPositionPreview, EngineError> {
+    let prices_cache = app.active_prices_cache.read().await;
+
+    check_open_price(app, &prices_cache, &request)?;
+
+    let id = request.id.clone().unwrap_or_default();
+    let mut position = make_active_position(make_open_command(&id, request), &prices_cache)?;
+    sanitize_sl_tp(&mut position.base_data);
+
+    return Ok(OpenPositionPreview {
+        required_margin: position.base_data.invest_amount,
+        trigger_prices: calculate_trigger_prices(&position),
+        position,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use trading_sdk::core::EngineCacheQueryBuilder;
+
+    use crate::{
+        position_manager_grpc::PositionManagerOpenPositionGrpcRequest,
+        test_utils::{create_test_app, create_test_bid_ask},
+    };
+
+    use super::preview_open_position;
+
+    #[tokio::test]
+    async fn test_preview_does_not_open_position() {
+        let app = create_test_app();
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.0998, 1.1));
+
+        let request = PositionManagerOpenPositionGrpcRequest {
+            asset_pair: "EURUSD".to_string(),
+            side: 0,
+            invest_amount: 100.0,
+            leverage: 10.0,
+            stop_out_percent: 90.0,
+            process_id: "preview".to_string(),
+            tp_in_profit: None,
+            sl_in_profit: None,
+            tp_in_asset_price: None,
+            sl_in_asset_price: None,
+            open_price: None,
+            open_bid_ask: None,
+            account_id: "account".to_string(),
+            trader_id: "trader".to_string(),
+            base: "EUR".to_string(),
+            quote: "USD".to_string(),
+            collateral_currency: "USD".to_string(),
+            id: None,
+            open_process_id: None,
+            metadata: HashMap::new(),
+            topping_up_percent: None,
+            margin_call_percent: Some(50.0),
+            trailing_stop_distance: None,
+            trailing_stop_percent: None,
+            break_even_profit: None,
+            break_even_percent: None,
+            break_even_offset: None,
+            max_slippage: None,
+            max_slippage_points: None,
+            point_size: None,
+        };
+
+        let preview = preview_open_position(&app, request).await.unwrap();
+
+        assert_eq!(preview.position.state.open_data.asset_open_price, 1.1);
+        assert_eq!(preview.required_margin, 100.0);
+        assert!(preview.trigger_prices.stop_out_price.unwrap() < 1.1);
+        assert!(
+            preview.trigger_prices.margin_call_price.unwrap()
+                > preview.trigger_prices.stop_out_price.unwrap()
+        );
+        assert!(preview.trigger_prices.topping_up_price.is_none());
+
+        let cache = app.active_positions_cache.read().await;
+        assert!(cache
+            .0
+            .query_positions(EngineCacheQueryBuilder::new().with_client("trader"))
+            .is_empty());
+    }
+}
@@ -6,7 +6,9 @@ use service_sdk::{
 };
 use trading_sdk::mt_engine::{return_topping_up, ActivePositionsCache};
 
-use crate::{map_active_to_sb_model, AppContext};
+use crate::{
+    map_active_to_sb_model, publish_position_updates, AppContext, OutboxMessage, PositionUpdate,
+};
 
 pub async fn process_topping_up_refund(
     app: Arc<AppContext>,
@@ -42,6 +44,8 @@ pub async fn process_topping_up_refund(
     );
 
     if let Some(updated_position) = updated_position {
+        publish_position_updates(&app, &[PositionUpdate::Updated(&updated_position)]).await;
+
         let sb_model = PositionPersistenceEvent {
             process_id: process_id.to_string(),
             update_position: Some(map_active_to_sb_model(updated_position)),
@@ -49,23 +53,21 @@ pub async fn process_topping_up_refund(
             create_position: None,
         };
 
-        app.active_positions_persistence_publisher
-            .publish(&sb_model, Some(my_telemetry))
-            .await
-            .unwrap();
+        app.persistence_outbox
+            .enqueue(OutboxMessage::ActivePosition(sb_model), Some(my_telemetry))
+            .await;
 
-        app.topping_up_publisher
-            .publish(
-                &PositionToppingUpEvent {
+        app.persistence_outbox
+            .enqueue(
+                OutboxMessage::ToppingUp(PositionToppingUpEvent {
                     process_id: process_id.to_string(),
                     position_id: id.to_string(),
                     trader_id: trader_id.to_string(),
                     account_id: account_id.to_string(),
                     delta: -topping_up_amount,
-                },
+                }),
                 Some(my_telemetry),
             )
-            .await
-            .unwrap();
+            .await;
     }
 }
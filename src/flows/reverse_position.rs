@@ -0,0 +1,249 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cfd_engine_sb_contracts::PositionPersistenceEvent;
+use service_sdk::my_telemetry::MyTelemetryContext;
+use trading_sdk::mt_engine::{
+    convert_position_to_closed, make_active_position, update_active_position_rate,
+    update_position_pl, MtPosition, MtPositionActiveState, MtPositionCloseReason,
+    MtPositionClosedState, MtPositionOpenCommand,
+};
+use uuid::Uuid;
+
+use crate::{
+    apply_closed_position_profit, map_active_to_sb_model, map_closed_to_sb,
+    publish_position_updates, utils::get_opposite_side, AppContext, EngineError, OutboxMessage,
+    PositionUpdate,
+};
+
+pub const REVERSED_FROM_POSITION_ID_METADATA_KEY: &str = "ReversedFromPositionId";
+
+pub async fn reverse_position(
+    app: &Arc<AppContext>,
+    trader_id: &str,
+    account_id: &str,
+    position_id: &str,
+    process_id: &str,
+    telemetry: &MyTelemetryContext,
+) -> Result<
+    (
+        MtPosition<MtPositionClosedState>,
+        MtPosition<MtPositionActiveState>,
+    ),
+    EngineError,
+> {
+    let prices_cache = app.active_prices_cache.read().await;
+    let mut cache = app.active_positions_cache.write().await;
+
+    let mut active_position = cache
+        .0
+        .get_by_id(position_id)
+        .ok_or(EngineError::PositionNotFound)?
+        .clone();
+
+    app.quote_age_limits
+        .check_position_price(&prices_cache, &active_position.base_data)?;
+
+    let bid_ask = prices_cache
+        .get_by_id(&active_position.base_data.asset_pair)
+        .ok_or(EngineError::NoLiquidity)?;
+
+    let open_command = create_reverse_open_command(&active_position, process_id);
+    let reversed = make_active_position(open_command.clone(), &prices_cache)?;
+
+    cache.0.remove_position(position_id);
+
+    update_active_position_rate(&mut active_position, &bid_ask);
+    update_position_pl(&mut active_position);
+
+    let closed = convert_position_to_closed(
+        active_position,
+        MtPositionCloseReason::ClientCommand,
+        process_id.to_string(),
+    );
+
+    cache.0.add_position(reversed.clone());
+
+    trade_log::trade_log!(
+        trader_id,
+        account_id,
+        process_id,
+        position_id,
+        "Executing reverse position",
+        telemetry.clone(),
+        "open_command" = &open_command,
+        "closed_position" = &closed,
+        "reversed_position" = &reversed
+    );
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: None,
+                close_position: Some(map_closed_to_sb(&closed)),
+                create_position: None,
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    apply_closed_position_profit(app, &closed).await;
+
+    app.persistence_outbox
+        .enqueue(
+            OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                process_id: process_id.to_string(),
+                update_position: None,
+                close_position: None,
+                create_position: Some(map_active_to_sb_model(reversed.clone())),
+            }),
+            Some(telemetry),
+        )
+        .await;
+
+    publish_position_updates(
+        app,
+        &[
+            PositionUpdate::Closed(&closed),
+            PositionUpdate::Opened(&reversed),
+        ],
+    )
+    .await;
+
+    return Ok((closed, reversed));
+}
+
+// SL/TP set in asset price are bound to the side of the position and would trigger right away
+// on the reversed one, so only the profit based levels are carried over.
+pub fn create_reverse_open_command(
+    position: &MtPosition<MtPositionActiveState>,
+    process_id: &str,
+) -> MtPositionOpenCommand {
+    let base_data = &position.base_data;
+
+    let mut metadata = base_data.metadata.clone().unwrap_or_else(HashMap::new);
+    metadata.insert(
+        REVERSED_FROM_POSITION_ID_METADATA_KEY.to_string(),
+        base_data.id.clone(),
+    );
+
+    MtPositionOpenCommand {
+        id: Uuid::new_v4().to_string(),
+        trader_id: base_data.trader_id.clone(),
+        account_id: base_data.account_id.clone(),
+        side: get_opposite_side(&base_data.side),
+        asset_pair: base_data.asset_pair.clone(),
+        base: base_data.base.clone(),
+        quote: base_data.quote.clone(),
+        collateral: base_data.collateral.clone(),
+        invest_amount: base_data.invest_amount,
+        leverage: base_data.leverage,
+        stop_out_percent: base_data.stop_out_percent,
+        process_id: process_id.to_string(),
+        pending_state: None,
+        tp_profit: base_data.tp_profit,
+        tp_price: None,
+        sl_profit: base_data.sl_profit,
+        sl_price: None,
+        topping_up_percent: base_data.topping_up_percent,
+        metadata: Some(metadata),
+        margin_call_percent: base_data.margin_call_percent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+    use trading_sdk::mt_engine::MtPositionSide;
+
+    use crate::{
+        test_utils::{create_test_active_position, create_test_app, create_test_bid_ask},
+        EngineError, QuoteAgeLimits,
+    };
+
+    use super::{reverse_position, REVERSED_FROM_POSITION_ID_METADATA_KEY};
+
+    #[tokio::test]
+    async fn test_reverse_position() {
+        let app = create_test_app();
+
+        app.active_prices_cache
+            .write()
+            .await
+            .handle_new(create_test_bid_ask(1.2, 1.2));
+
+        let mut position = create_test_active_position("id", "trader", "account");
+        position.base_data.sl_price = Some(1.0);
+        position.base_data.tp_profit = Some(30.0);
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(position);
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+
+        let (closed, reversed) =
+            reverse_position(&app, "trader", "account", "id", "process", &telemetry)
+                .await
+                .unwrap();
+
+        assert_eq!(closed.base_data.id, "id");
+        assert_eq!(closed.state.close_process_id, "process");
+
+        assert!(matches!(reversed.base_data.side, MtPositionSide::Sell));
+        assert_eq!(reversed.base_data.invest_amount, 100.0);
+        assert_eq!(reversed.base_data.leverage, 10.0);
+        assert_eq!(reversed.base_data.sl_price, None);
+        assert_eq!(reversed.base_data.tp_profit, Some(30.0));
+        assert_eq!(
+            reversed
+                .base_data
+                .metadata
+                .as_ref()
+                .unwrap()
+                .get(REVERSED_FROM_POSITION_ID_METADATA_KEY)
+                .unwrap(),
+            "id"
+        );
+
+        let cache = app.active_positions_cache.read().await;
+        assert!(cache.0.get_by_id("id").is_none());
+        assert!(cache.0.get_by_id(&reversed.base_data.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_rejected_on_stale_price() {
+        let mut app = create_test_app();
+        Arc::get_mut(&mut app).unwrap().quote_age_limits =
+            QuoteAgeLimits::new(Some(10), &HashMap::new());
+
+        let mut bid_ask = create_test_bid_ask(1.2, 1.2);
+        bid_ask.date = DateTimeAsMicroseconds::new(
+            DateTimeAsMicroseconds::now().unix_microseconds - 60_000_000,
+        );
+        app.active_prices_cache.write().await.handle_new(bid_ask);
+
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position("id", "trader", "account"));
+
+        let telemetry = service_sdk::my_telemetry::MyTelemetryContext::new();
+
+        let result = reverse_position(&app, "trader", "account", "id", "process", &telemetry).await;
+
+        assert!(matches!(result, Err(EngineError::StalePrice)));
+        assert!(app
+            .active_positions_cache
+            .read()
+            .await
+            .0
+            .get_by_id("id")
+            .is_some());
+        assert_eq!(app.persistence_outbox.len().await, 0);
+    }
+}
@@ -0,0 +1,94 @@
+use trading_sdk::mt_engine::{MtBidAsk, MtPositionSide};
+
+use crate::{position_manager_grpc::PositionManagerBidAsk, utils::get_open_price, EngineError};
+
+const SLIPPAGE_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaxSlippage {
+    Price(f64),
+    Points { points: f64, point_size: f64 },
+}
+
+impl MaxSlippage {
+    pub fn from_request(
+        price: Option<f64>,
+        points: Option<f64>,
+        point_size: Option<f64>,
+    ) -> Option<Self> {
+        if let Some(price) = price {
+            return Some(MaxSlippage::Price(price));
+        }
+
+        return Some(MaxSlippage::Points {
+            points: points?,
+            point_size: point_size?,
+        });
+    }
+
+    pub fn get_price_distance(&self) -> f64 {
+        match self {
+            MaxSlippage::Price(price) => *price,
+            MaxSlippage::Points { points, point_size } => points * point_size,
+        }
+    }
+}
+
+// The price the client saw: the explicit open price, otherwise the side of the quote the
+// position would be opened at.
+pub fn get_client_open_price(
+    open_price: Option<f64>,
+    open_bid_ask: Option<&PositionManagerBidAsk>,
+    side: &MtPositionSide,
+) -> Option<f64> {
+    if open_price.is_some() {
+        return open_price;
+    }
+
+    return open_bid_ask.map(|x| match side {
+        MtPositionSide::Buy => x.ask,
+        MtPositionSide::Sell => x.bid,
+    });
+}
+
+// Deviation is checked both ways, a better fill than the client saw is rejected too, like a
+// requote.
+pub fn check_slippage(
+    max_slippage: &MaxSlippage,
+    client_price: f64,
+    bid_ask: &MtBidAsk,
+    side: &MtPositionSide,
+) -> Result<(), EngineError> {
+    let fill_price = get_open_price(bid_ask, side);
+
+    if (fill_price - client_price).abs() > max_slippage.get_price_distance() + SLIPPAGE_TOLERANCE {
+        return Err(EngineError::PriceChanged);
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::MtPositionSide;
+
+    use crate::{test_utils::create_test_bid_ask, EngineError};
+
+    use super::{check_slippage, MaxSlippage};
+
+    #[test]
+    fn test_check_slippage() {
+        let bid_ask = create_test_bid_ask(1.1000, 1.1002);
+        let max_slippage = MaxSlippage::from_request(None, Some(5.0), Some(0.0001)).unwrap();
+
+        assert!(check_slippage(&max_slippage, 1.1000, &bid_ask, &MtPositionSide::Buy).is_ok());
+        assert!(matches!(
+            check_slippage(&max_slippage, 1.0990, &bid_ask, &MtPositionSide::Buy),
+            Err(EngineError::PriceChanged)
+        ));
+        assert!(matches!(
+            check_slippage(&max_slippage, 1.1010, &bid_ask, &MtPositionSide::Sell),
+            Err(EngineError::PriceChanged)
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, time::Duration};
+
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+use trading_sdk::mt_engine::{MtBidAsk, MtBidAskCache, MtPositionBaseData};
+
+use crate::EngineError;
+
+// Instruments without a limit of their own use the default one. Without any limit quotes never
+// go stale, which keeps the old behaviour for setups that do not configure it.
+pub struct QuoteAgeLimits {
+    default_max_age: Option<Duration>,
+    max_age_by_instrument: HashMap<String, Duration>,
+}
+
+impl QuoteAgeLimits {
+    pub fn new(
+        default_max_age_sec: Option<u64>,
+        max_age_sec_by_instrument: &HashMap<String, u64>,
+    ) -> Self {
+        Self {
+            default_max_age: default_max_age_sec.map(Duration::from_secs),
+            max_age_by_instrument: max_age_sec_by_instrument
+                .iter()
+                .map(|(asset_pair, sec)| (asset_pair.clone(), Duration::from_secs(*sec)))
+                .collect(),
+        }
+    }
+
+    pub fn get_max_age(&self, asset_pair: &str) -> Option<Duration> {
+        return self
+            .max_age_by_instrument
+            .get(asset_pair)
+            .cloned()
+            .or(self.default_max_age);
+    }
+
+    pub fn is_stale(
+        &self,
+        asset_pair: &str,
+        quote_date: DateTimeAsMicroseconds,
+        now: DateTimeAsMicroseconds,
+    ) -> bool {
+        let max_age = match self.get_max_age(asset_pair) {
+            Some(max_age) => max_age,
+            None => return false,
+        };
+
+        return now.unix_microseconds - quote_date.unix_microseconds > max_age.as_micros() as i64;
+    }
+
+    pub fn is_quote_stale(&self, bid_ask: &MtBidAsk, now: DateTimeAsMicroseconds) -> bool {
+        return self.is_stale(&bid_ask.asset_pair, bid_ask.date, now);
+    }
+
+    // Checks the instrument quote along with the base and quote conversions to the collateral
+    // the position is valued with, looked up the same way the engine does. A missing quote is
+    // left to the engine, which reports it as no liquidity.
+    pub fn check_price(
+        &self,
+        prices_cache: &MtBidAskCache,
+        asset_pair: &str,
+        base: &str,
+        quote: &str,
+        collateral: &str,
+    ) -> Result<(), EngineError> {
+        if let Some(bid_ask) = prices_cache.get_by_id(asset_pair) {
+            self.check_quote(&bid_ask)?;
+        }
+
+        for currency in [base, quote] {
+            if currency == collateral {
+                continue;
+            }
+
+            let conversion = prices_cache
+                .get_base_quote(currency, collateral)
+                .or_else(|| prices_cache.get_base_quote(collateral, currency));
+
+            if let Some(bid_ask) = conversion {
+                self.check_quote(&bid_ask)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn check_position_price(
+        &self,
+        prices_cache: &MtBidAskCache,
+        base_data: &MtPositionBaseData,
+    ) -> Result<(), EngineError> {
+        return self.check_price(
+            prices_cache,
+            &base_data.asset_pair,
+            &base_data.base,
+            &base_data.quote,
+            &base_data.collateral,
+        );
+    }
+
+    fn check_quote(&self, bid_ask: &MtBidAsk) -> Result<(), EngineError> {
+        if self.is_quote_stale(bid_ask, DateTimeAsMicroseconds::now()) {
+            service_sdk::metrics::counter!("stale_price_rejects", "asset_pair" => bid_ask.asset_pair.clone())
+                .increment(1);
+            return Err(EngineError::StalePrice);
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+    use trading_sdk::mt_engine::{MtBidAsk, MtBidAskCache};
+
+    use crate::EngineError;
+
+    use super::QuoteAgeLimits;
+
+    fn create_bid_ask(base: &str, quote: &str, date: DateTimeAsMicroseconds) -> MtBidAsk {
+        MtBidAsk {
+            asset_pair: format!("{}{}", base, quote),
+            bid: 1.0,
+            ask: 1.0,
+            date,
+            base: base.to_string(),
+            quote: quote.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_quote_age_limits() {
+        let limits = QuoteAgeLimits::new(Some(10), &HashMap::from([("BTCUSD".to_string(), 60)]));
+        let quote_date = DateTimeAsMicroseconds::new(0);
+        let now = DateTimeAsMicroseconds::new(30_000_000);
+
+        assert!(limits.is_stale("EURUSD", quote_date, now));
+        assert!(!limits.is_stale("BTCUSD", quote_date, now));
+
+        let limits = QuoteAgeLimits::new(None, &HashMap::new());
+        assert!(!limits.is_stale("EURUSD", quote_date, now));
+    }
+
+    #[test]
+    fn test_check_price_includes_collateral_conversion() {
+        let limits = QuoteAgeLimits::new(Some(10), &HashMap::new());
+        let now = DateTimeAsMicroseconds::now();
+        let stale_date = DateTimeAsMicroseconds::new(now.unix_microseconds - 60_000_000);
+
+        let mut prices_cache = MtBidAskCache::new();
+        prices_cache.handle_new(create_bid_ask("EUR", "USD", now));
+        prices_cache.handle_new(create_bid_ask("GBP", "USD", now));
+
+        assert!(limits
+            .check_price(&prices_cache, "EURUSD", "EUR", "USD", "USD")
+            .is_ok());
+        assert!(limits
+            .check_price(&prices_cache, "EURUSD", "EUR", "USD", "GBP")
+            .is_ok());
+
+        prices_cache.handle_new(create_bid_ask("GBP", "USD", stale_date));
+
+        assert!(limits
+            .check_price(&prices_cache, "EURUSD", "EUR", "USD", "USD")
+            .is_ok());
+        assert!(matches!(
+            limits.check_price(&prices_cache, "EURUSD", "EUR", "USD", "GBP"),
+            Err(EngineError::StalePrice)
+        ));
+    }
+}
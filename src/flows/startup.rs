@@ -4,7 +4,11 @@ use service_sdk::my_telemetry::MyTelemetryContext;
 use tokio::sync::RwLock;
 use trading_sdk::mt_engine::{ActivePositionsCache, MtBidAskCache, PendingPositionsCache};
 
-use crate::{map_active_persistence, PositionManagerPersistenceClient, map_pending_persistence};
+use crate::{
+    get_pending_expire_at, map_active_persistence, map_active_sb_to_persistence,
+    map_pending_persistence, map_pending_sb_to_persistence, OutboxMessage, PendingExpirationsCache,
+    PersistenceOutbox, PositionManagerPersistenceClient,
+};
 
 pub async fn load_prices_cache(
     grpc_client: &PositionManagerPersistenceClient,
@@ -55,7 +59,7 @@ pub async fn load_pending_positions(
     grpc_client: &PositionManagerPersistenceClient,
     prices_cache: &MtBidAskCache,
     telemetry: &MyTelemetryContext,
-) -> PendingPositionsCache {
+) -> (PendingPositionsCache, PendingExpirationsCache) {
     telemetry.start_event_tracking("load_pending_positions");
 
     let positions = grpc_client
@@ -64,14 +68,226 @@ pub async fn load_pending_positions(
         .unwrap();
 
     let mut positions_cache = PendingPositionsCache::new();
+    let mut expirations = PendingExpirationsCache::new();
 
     if let Some(positions) = positions {
         for position in positions {
-            positions_cache
-                .0
-                .add_position(map_pending_persistence(position, prices_cache).await);
+            let position = map_pending_persistence(position, prices_cache).await;
+
+            if let Some(expire_at) = get_pending_expire_at(&position.base_data) {
+                expirations.arm(&position.base_data.id, expire_at);
+            }
+
+            positions_cache.0.add_position(position);
         }
     }
 
-    return positions_cache;
+    return (positions_cache, expirations);
+}
+
+// Orders that were awaiting confirmation before the restart are still pending in the
+// persistence. They are moved back out of the pending cache so they are not triggered twice.
+pub fn restore_awaiting_confirmation(
+    pending_cache: &mut PendingPositionsCache,
+    awaiting_ids: &[String],
+) -> PendingPositionsCache {
+    let mut awaiting_cache = PendingPositionsCache::new();
+
+    for id in awaiting_ids {
+        if let Some(position) = pending_cache.0.remove_position(id) {
+            awaiting_cache.0.add_position(position);
+        }
+    }
+
+    return awaiting_cache;
+}
+
+// Events still in the outbox journal may not have reached the persistence before the restart,
+// so the loaded snapshot can be behind them. They are applied on top of it in outbox order.
+// The bus models carry no metadata, so a replaced position keeps the metadata it was loaded with.
+pub async fn apply_outbox_journal(
+    outbox: &PersistenceOutbox,
+    prices_cache: &MtBidAskCache,
+    active_cache: &mut ActivePositionsCache,
+    pending_cache: &mut PendingPositionsCache,
+    expirations: &mut PendingExpirationsCache,
+) {
+    let items = outbox.peek_batch(usize::MAX).await;
+
+    for item in &items {
+        match &item.message {
+            OutboxMessage::ActivePosition(event) => {
+                if let Some(closed) = &event.close_position {
+                    active_cache.0.remove_position(&closed.id);
+                }
+
+                let upserted = event
+                    .create_position
+                    .as_ref()
+                    .or(event.update_position.as_ref());
+
+                if let Some(upserted) = upserted {
+                    let mut position = map_active_persistence(
+                        map_active_sb_to_persistence(upserted.clone()),
+                        prices_cache,
+                    )
+                    .await;
+
+                    if let Some(loaded) = active_cache.0.remove_position(&upserted.id) {
+                        position.base_data.metadata = loaded.base_data.metadata;
+                    }
+
+                    active_cache.0.add_position(position);
+                }
+            }
+            OutboxMessage::PendingPosition(event) => {
+                let removed = event.cancel.as_ref().or(event.execute.as_ref());
+
+                if let Some(removed) = removed {
+                    pending_cache.0.remove_position(&removed.id);
+                    expirations.disarm(&removed.id);
+                }
+
+                let upserted = event.create.as_ref().or(event.update.as_ref());
+
+                if let Some(upserted) = upserted {
+                    let mut position = map_pending_persistence(
+                        map_pending_sb_to_persistence(upserted.clone()),
+                        prices_cache,
+                    )
+                    .await;
+
+                    if let Some(loaded) = pending_cache.0.remove_position(&upserted.id) {
+                        position.base_data.metadata = loaded.base_data.metadata;
+                    }
+
+                    match get_pending_expire_at(&position.base_data) {
+                        Some(expire_at) => expirations.arm(&position.base_data.id, expire_at),
+                        None => expirations.disarm(&position.base_data.id),
+                    }
+
+                    pending_cache.0.add_position(position);
+                }
+            }
+            OutboxMessage::ToppingUp(_) => {}
+        }
+    }
+
+    if items.len() > 0 {
+        println!(
+            "Applied {} journaled outbox messages to the loaded data",
+            items.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cfd_engine_sb_contracts::{PendingPositionPersistenceEvent, PositionPersistenceEvent};
+    use trading_sdk::mt_engine::{ActivePositionsCache, MtBidAskCache, PendingPositionsCache};
+
+    use crate::{
+        map_active_to_sb_model, map_pending_to_sb_model,
+        test_utils::{
+            create_test_active_position, create_test_bid_ask, create_test_pending_position,
+        },
+        OutboxMessage, PendingExpirationsCache, PersistenceOutbox,
+    };
+
+    use super::apply_outbox_journal;
+
+    #[tokio::test]
+    async fn test_journaled_events_are_applied_to_loaded_data() {
+        let mut prices_cache = MtBidAskCache::new();
+        prices_cache.handle_new(create_test_bid_ask(1.1, 1.1));
+
+        let mut active_cache = ActivePositionsCache::new();
+        let mut pending_cache = PendingPositionsCache::new();
+        let mut expirations = PendingExpirationsCache::new();
+
+        let closed = create_test_active_position("closed", "trader", "account");
+        let mut updated = create_test_active_position("updated", "trader", "account");
+        updated.base_data.metadata =
+            Some(HashMap::from([("key".to_string(), "value".to_string())]));
+        let cancelled = create_test_pending_position("cancelled", "trader", "account");
+
+        active_cache.0.add_position(closed.clone());
+        active_cache.0.add_position(updated.clone());
+        pending_cache.0.add_position(cancelled.clone());
+
+        let outbox = PersistenceOutbox::new(None);
+
+        outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                    process_id: "close".to_string(),
+                    update_position: None,
+                    close_position: Some(map_active_to_sb_model(closed)),
+                    create_position: None,
+                }),
+                None,
+            )
+            .await;
+
+        updated.base_data.invest_amount = 200.0;
+        outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                    process_id: "update".to_string(),
+                    update_position: Some(map_active_to_sb_model(updated)),
+                    close_position: None,
+                    create_position: None,
+                }),
+                None,
+            )
+            .await;
+
+        outbox
+            .enqueue(
+                OutboxMessage::ActivePosition(PositionPersistenceEvent {
+                    process_id: "create".to_string(),
+                    update_position: None,
+                    close_position: None,
+                    create_position: Some(map_active_to_sb_model(create_test_active_position(
+                        "created", "trader", "account",
+                    ))),
+                }),
+                None,
+            )
+            .await;
+
+        outbox
+            .enqueue(
+                OutboxMessage::PendingPosition(PendingPositionPersistenceEvent {
+                    process_id: "cancel".to_string(),
+                    cancel: Some(map_pending_to_sb_model(cancelled)),
+                    cancel_reason: None,
+                    create: None,
+                    update: None,
+                    execute: None,
+                }),
+                None,
+            )
+            .await;
+
+        apply_outbox_journal(
+            &outbox,
+            &prices_cache,
+            &mut active_cache,
+            &mut pending_cache,
+            &mut expirations,
+        )
+        .await;
+
+        assert!(active_cache.0.get_by_id("closed").is_none());
+        assert!(active_cache.0.get_by_id("created").is_some());
+        assert!(pending_cache.0.get_by_id("cancelled").is_none());
+
+        let updated = active_cache.0.get_by_id("updated").unwrap();
+        assert_eq!(updated.base_data.invest_amount, 200.0);
+        let metadata = updated.base_data.metadata.as_ref().unwrap();
+        assert_eq!(metadata.get("key").unwrap(), "value");
+    }
 }
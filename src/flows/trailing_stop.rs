@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+use trading_sdk::mt_engine::{MtPosition, MtPositionActiveState, MtPositionSide};
+
+use crate::utils::is_sl_improved;
+
+pub const TRAILING_STOP_DISTANCE_METADATA_KEY: &str = "TrailingStopDistance";
+pub const TRAILING_STOP_PERCENT_METADATA_KEY: &str = "TrailingStopPercent";
+
+// The base data of the engine has no place for the trailing stop, so it lives in the
+// position metadata and follows the position through pending execution and splits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingStop {
+    Distance(f64),
+    Percent(f64),
+}
+
+impl TrailingStop {
+    pub fn from_request(distance: Option<f64>, percent: Option<f64>) -> Option<Self> {
+        if let Some(distance) = distance {
+            return Some(TrailingStop::Distance(distance));
+        }
+
+        return percent.map(TrailingStop::Percent);
+    }
+
+    pub fn from_metadata(metadata: Option<&HashMap<String, String>>) -> Option<Self> {
+        let metadata = metadata?;
+
+        if let Some(distance) = metadata.get(TRAILING_STOP_DISTANCE_METADATA_KEY) {
+            return distance.parse().ok().map(TrailingStop::Distance);
+        }
+
+        return metadata
+            .get(TRAILING_STOP_PERCENT_METADATA_KEY)
+            .and_then(|x| x.parse().ok())
+            .map(TrailingStop::Percent);
+    }
+
+    pub fn write_metadata(trailing_stop: Option<Self>, metadata: &mut HashMap<String, String>) {
+        metadata.remove(TRAILING_STOP_DISTANCE_METADATA_KEY);
+        metadata.remove(TRAILING_STOP_PERCENT_METADATA_KEY);
+
+        match trailing_stop {
+            Some(TrailingStop::Distance(distance)) => {
+                metadata.insert(
+                    TRAILING_STOP_DISTANCE_METADATA_KEY.to_string(),
+                    distance.to_string(),
+                );
+            }
+            Some(TrailingStop::Percent(percent)) => {
+                metadata.insert(
+                    TRAILING_STOP_PERCENT_METADATA_KEY.to_string(),
+                    percent.to_string(),
+                );
+            }
+            None => {}
+        }
+    }
+
+    pub fn get_distance(&self, price: f64) -> f64 {
+        match self {
+            TrailingStop::Distance(distance) => *distance,
+            TrailingStop::Percent(percent) => price * percent / 100.0,
+        }
+    }
+}
+
+pub fn set_position_trailing_stop(
+    metadata: &mut Option<HashMap<String, String>>,
+    trailing_stop: Option<TrailingStop>,
+) {
+    if trailing_stop.is_none() && metadata.is_none() {
+        return;
+    }
+
+    TrailingStop::write_metadata(trailing_stop, metadata.get_or_insert_with(HashMap::new));
+}
+
+// Moves sl_price after the close price of the position, only ever in the profitable direction.
+// Returns true when the stop was moved.
+pub fn update_trailing_stop(
+    position: &mut MtPosition<MtPositionActiveState>,
+    process_id: &str,
+) -> bool {
+    let trailing_stop = match TrailingStop::from_metadata(position.base_data.metadata.as_ref()) {
+        Some(trailing_stop) => trailing_stop,
+        None => return false,
+    };
+
+    let price = position.state.asset_active_price;
+    let distance = trailing_stop.get_distance(price);
+
+    let sl_price = match position.base_data.side {
+        MtPositionSide::Buy => price - distance,
+        MtPositionSide::Sell => price + distance,
+    };
+
+    if !is_sl_improved(
+        &position.base_data.side,
+        sl_price,
+        position.base_data.sl_price,
+    ) {
+        return false;
+    }
+
+    position.base_data.sl_price = Some(sl_price);
+    position.base_data.last_update_date = DateTimeAsMicroseconds::now();
+    position.base_data.last_update_process_id = process_id.to_string();
+
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::create_test_active_position;
+
+    use super::{set_position_trailing_stop, update_trailing_stop, TrailingStop};
+
+    #[test]
+    fn test_trailing_stop_moves_only_forward() {
+        let mut position = create_test_active_position("id", "trader", "account");
+        set_position_trailing_stop(
+            &mut position.base_data.metadata,
+            Some(TrailingStop::Distance(0.1)),
+        );
+
+        position.state.asset_active_price = 1.2;
+        assert!(update_trailing_stop(&mut position, "process"));
+        assert!((position.base_data.sl_price.unwrap() - 1.1).abs() < 1e-12);
+
+        position.state.asset_active_price = 1.15;
+        assert!(!update_trailing_stop(&mut position, "process"));
+        assert!((position.base_data.sl_price.unwrap() - 1.1).abs() < 1e-12);
+
+        position.state.asset_active_price = 1.3;
+        assert!(update_trailing_stop(&mut position, "process"));
+        assert!((position.base_data.sl_price.unwrap() - 1.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_trailing_stop_metadata_round_trip() {
+        let mut metadata = None;
+
+        set_position_trailing_stop(&mut metadata, Some(TrailingStop::Percent(2.5)));
+        assert_eq!(
+            TrailingStop::from_metadata(metadata.as_ref()),
+            Some(TrailingStop::Percent(2.5))
+        );
+
+        set_position_trailing_stop(&mut metadata, None);
+        assert_eq!(TrailingStop::from_metadata(metadata.as_ref()), None);
+    }
+}
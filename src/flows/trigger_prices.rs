@@ -0,0 +1,114 @@
+use serde::Serialize;
+use trading_sdk::mt_engine::{MtPosition, MtPositionActiveState, MtPositionSide};
+
+// Asset prices at which the engine acts on the position. A price is None when the threshold is
+// not configured or can not be reached, e.g. the stop out of a low leverage buy below zero.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PositionTriggerPrices {
+    pub stop_out_price: Option<f64>,
+    pub margin_call_price: Option<f64>,
+    pub topping_up_price: Option<f64>,
+}
+
+// The profit is the asset price change times the volume in base units, converted from quote to
+// collateral at the current quote-collateral price. The volume is the invest amount * leverage
+// converted to base units at the base-collateral open price.
+pub fn get_price_for_profit(
+    position: &MtPosition<MtPositionActiveState>,
+    profit: f64,
+) -> Option<f64> {
+    let open_data = &position.state.open_data;
+    let base_volume = position.base_data.invest_amount * position.base_data.leverage
+        / open_data.base_collateral_open_price;
+    let quote_collateral_price = position.state.quote_collateral_active_price;
+
+    if !base_volume.is_finite() || base_volume <= 0.0 || quote_collateral_price <= 0.0 {
+        return None;
+    }
+
+    let price_change = profit / (base_volume * quote_collateral_price);
+
+    let price = match position.base_data.side {
+        MtPositionSide::Buy => open_data.asset_open_price + price_change,
+        MtPositionSide::Sell => open_data.asset_open_price - price_change,
+    };
+
+    if !price.is_finite() || price <= 0.0 {
+        return None;
+    }
+
+    return Some(price);
+}
+
+fn get_price_for_loss_percent(
+    position: &MtPosition<MtPositionActiveState>,
+    margin: f64,
+    percent: f64,
+) -> Option<f64> {
+    return get_price_for_profit(position, -margin * percent / 100.0);
+}
+
+// The reserved topping-up funds add to the margin the stop out is measured against, while the
+// margin call and the topping-up request are measured against the invest amount only.
+pub fn calculate_trigger_prices(
+    position: &MtPosition<MtPositionActiveState>,
+) -> PositionTriggerPrices {
+    let invest_amount = position.base_data.invest_amount;
+    let stop_out_margin = invest_amount + position.state.topping_up.unwrap_or(0.0);
+
+    return PositionTriggerPrices {
+        stop_out_price: get_price_for_loss_percent(
+            position,
+            stop_out_margin,
+            position.base_data.stop_out_percent,
+        ),
+        margin_call_price: position
+            .base_data
+            .margin_call_percent
+            .and_then(|percent| get_price_for_loss_percent(position, invest_amount, percent)),
+        topping_up_price: position
+            .base_data
+            .topping_up_percent
+            .and_then(|percent| get_price_for_loss_percent(position, invest_amount, percent)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use trading_sdk::mt_engine::MtPositionSide;
+
+    use crate::test_utils::create_test_active_position;
+
+    use super::calculate_trigger_prices;
+
+    fn assert_price(price: Option<f64>, expected: f64) {
+        assert!(
+            (price.unwrap() - expected).abs() < 1e-9,
+            "{:?} != {}",
+            price,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_trigger_prices() {
+        let mut position = create_test_active_position("id", "trader", "account");
+
+        let prices = calculate_trigger_prices(&position);
+        assert_price(prices.stop_out_price, 1.01);
+        assert_price(prices.margin_call_price, 1.05);
+        assert_price(prices.topping_up_price, 1.09);
+
+        position.base_data.side = MtPositionSide::Sell;
+        position.state.quote_collateral_active_price = 2.0;
+        position.state.topping_up = Some(100.0);
+
+        let prices = calculate_trigger_prices(&position);
+        assert_price(prices.stop_out_price, 1.19);
+        assert_price(prices.margin_call_price, 1.125);
+
+        position.base_data.side = MtPositionSide::Buy;
+        position.base_data.leverage = 0.5;
+        assert!(calculate_trigger_prices(&position).stop_out_price.is_none());
+    }
+}
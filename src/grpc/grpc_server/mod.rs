@@ -1,5 +1,7 @@
 mod position_manager_grpc_server;
 mod server;
+mod position_updates_stream;
 
 pub use position_manager_grpc_server::*;
-pub use server::*;
\ No newline at end of file
+pub use server::*;
+pub use position_updates_stream::*;
\ No newline at end of file
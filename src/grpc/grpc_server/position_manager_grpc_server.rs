@@ -1,25 +1,49 @@
 use crate::{
-    cancel_pending, charge_swaps, close_position, confirm_pending_execution,
-    map_active_to_sb_model, open_pending, open_position,
+    cancel_pending, charge_swaps, close_by, close_position, confirm_pending_execution,
+    get_account_summary, get_profit_update_interval, increase_position, map_active_to_sb_model,
+    modify_pending, open_pending, open_position, partial_close_position,
     position_manager_grpc::{
         position_manager_grpc_service_server::PositionManagerGrpcService,
         PositionManagerActivePositionGrpcModel, PositionManagerCancelPendingGrpcRequest,
         PositionManagerCancelPendingGrpcResponse, PositionManagerChargeSwapGrpcRequest,
-        PositionManagerChargeSwapGrpcResponse, PositionManagerClosePositionGrpcRequest,
+        PositionManagerChargeSwapGrpcResponse, PositionManagerCloseByGrpcRequest,
+        PositionManagerCloseByGrpcResponse, PositionManagerClosePositionGrpcRequest,
         PositionManagerClosePositionGrpcResponse, PositionManagerConfirmPendingExecuteGrpcRequest,
         PositionManagerConfirmPendingExecuteGrpcResponse,
+        PositionManagerGetAccountSummaryGrpcRequest, PositionManagerGetAccountSummaryGrpcResponse,
         PositionManagerGetActivePositionGrpcRequest, PositionManagerGetActivePositionGrpcResponse,
         PositionManagerGetActivePositionsGrpcRequest, PositionManagerGetPendingPositionGrpcRequest,
         PositionManagerGetPendingPositionGrpcResponse,
-        PositionManagerGetPendingPositionsGrpcRequest, PositionManagerOpenPendingGrpcRequest,
+        PositionManagerGetPendingPositionsGrpcRequest,
+        PositionManagerGetQuarantinedTicksGrpcRequest,
+        PositionManagerGetQuarantinedTicksGrpcResponse, PositionManagerIncreasePositionGrpcRequest,
+        PositionManagerIncreasePositionGrpcResponse, PositionManagerModifyPendingGrpcRequest,
+        PositionManagerModifyPendingGrpcResponse, PositionManagerOpenPendingGrpcRequest,
         PositionManagerOpenPendingGrpcResponse, PositionManagerOpenPositionGrpcRequest,
         PositionManagerOpenPositionGrpcResponse, PositionManagerOperationsCodes,
-        PositionManagerPendingPositionGrpcModel, PositionManagerTopUpPositionGrpcRequest,
-        PositionManagerTopUpPositionGrpcResponse, PositionManagerUpdateSlTpGrpcRequest,
+        PositionManagerPartialClosePositionGrpcRequest,
+        PositionManagerPartialClosePositionGrpcResponse, PositionManagerPendingPositionGrpcModel,
+        PositionManagerPreviewOpenPositionGrpcResponse,
+        PositionManagerRejectPendingExecuteGrpcRequest,
+        PositionManagerRejectPendingExecuteGrpcResponse,
+        PositionManagerRejectPendingExecutionAction, PositionManagerReversePositionGrpcRequest,
+        PositionManagerReversePositionGrpcResponse,
+        PositionManagerSubscribeAccountPositionsGrpcRequest,
+        PositionManagerTopUpPositionGrpcRequest, PositionManagerTopUpPositionGrpcResponse,
+        PositionManagerUpdateAccountBalanceGrpcRequest,
+        PositionManagerUpdateAccountBalanceGrpcResponse, PositionManagerUpdateSlTpGrpcRequest,
         PositionManagerUpdateSlTpGrpcResponse, PositionManagerUpdateToppingUpGrpcRequest,
         PositionManagerUpdateToppingUpGrpcResponse,
     },
-    GrpcService,
+    preview_open_position, publish_position_updates, reject_pending_execution, reverse_position,
+    set_position_break_even_stop, set_position_trailing_stop, subscribe_account_positions,
+    update_account_balance, validate_charge_swap_request, validate_close_by_request,
+    validate_increase_position_request, validate_modify_pending_request,
+    validate_open_pending_request, validate_open_position_request, validate_partial_close_request,
+    validate_top_up_position_request, validate_update_account_balance_request,
+    validate_update_sl_tp_request, AccountBalance, BreakEvenStop, EngineError, GrpcService,
+    IdempotencyKey, IdempotencyResult, OutboxMessage, PartialCloseVolume, PositionUpdate,
+    PositionUpdatesStream, TrailingStop,
 };
 use cfd_engine_sb_contracts::{PositionPersistenceEvent, PositionToppingUpEvent};
 use my_grpc_extensions::server::with_telemetry;
@@ -32,11 +56,13 @@ use trading_sdk::{
     core::EngineCacheQueryBuilder,
     mt_engine::{apply_position_topping_up, sanitize_sl_tp, MtPositionCloseReason},
 };
+use uuid::Uuid;
 
 #[tonic::async_trait]
 impl PositionManagerGrpcService for GrpcService {
     generate_server_stream!(stream_name: "GetAccountActivePositionsStream", item_name: "PositionManagerActivePositionGrpcModel");
     generate_server_stream!(stream_name: "GetAccountPendingPositionsStream", item_name: "PositionManagerPendingPositionGrpcModel");
+    generate_server_stream!(stream_name: "SubscribeAccountPositionsStream", item_name: "PositionManagerPositionUpdateGrpcModel");
 
     #[with_telemetry]
     async fn open_position(
@@ -45,6 +71,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerOpenPositionGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "OpenPosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
         trade_log::trade_log!(
             &request.trader_id,
             &request.account_id,
@@ -55,18 +95,56 @@ impl PositionManagerGrpcService for GrpcService {
             "request" = &request
         );
 
+        if let Err(status) = validate_open_position_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                "",
+                "Open position request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerOpenPositionGrpcResponse {
+                position: None,
+                closed_positions: vec![],
+                status: status as i32,
+                current_bid_ask: None,
+            }));
+        }
+
         let open_position_result =
             open_position(&self.app, request.clone(), &MyTelemetryContext::new()).await;
         let response = match open_position_result.clone() {
-            Ok(position) => PositionManagerOpenPositionGrpcResponse {
-                position: Some(position.into()),
+            Ok(result) => PositionManagerOpenPositionGrpcResponse {
+                position: result.position.map(|x| x.into()),
+                closed_positions: result
+                    .closed_positions
+                    .into_iter()
+                    .map(|x| x.into())
+                    .collect(),
                 status: PositionManagerOperationsCodes::Ok as i32,
+                current_bid_ask: None,
             },
             Err(error) => {
+                let current_bid_ask = match error {
+                    EngineError::PriceChanged => self
+                        .app
+                        .active_prices_cache
+                        .read()
+                        .await
+                        .get_by_id(&request.asset_pair)
+                        .map(|x| x.as_ref().clone().into()),
+                    _ => None,
+                };
+
                 let grpc_status: PositionManagerOperationsCodes = error.into();
                 PositionManagerOpenPositionGrpcResponse {
                     position: None,
+                    closed_positions: vec![],
                     status: grpc_status as i32,
+                    current_bid_ask,
                 }
             }
         };
@@ -82,6 +160,7 @@ impl PositionManagerGrpcService for GrpcService {
             "open_position_result" = &open_position_result
         );
 
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -92,6 +171,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerGetActivePositionGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerGetActivePositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let result = {
             let reed = self.app.active_positions_cache.read().await;
             let position = reed.0.get_by_id(&request.position_id);
@@ -117,6 +210,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerClosePositionGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "ClosePosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
         trade_log::trade_log!(
             &request.trader_id,
             &request.account_id,
@@ -127,6 +234,20 @@ impl PositionManagerGrpcService for GrpcService {
             "request" = &request
         );
 
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerClosePositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let closed_position = close_position(
             &self.app,
             &request.trader_id,
@@ -163,6 +284,423 @@ impl PositionManagerGrpcService for GrpcService {
             "response" = &response
         );
 
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn close_by(
+        &self,
+        request: tonic::Request<PositionManagerCloseByGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerCloseByGrpcResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "CloseBy",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Got close by request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = validate_close_by_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Close by request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerCloseByGrpcResponse {
+                closed_positions: vec![],
+                remaining_position: None,
+                status: status as i32,
+            }));
+        }
+
+        for position_id in [&request.position_id, &request.opposite_position_id] {
+            if let Err(status) = self
+                .check_active_position_owner(position_id, &request.trader_id, &request.account_id)
+                .await
+            {
+                return Ok(tonic::Response::new(PositionManagerCloseByGrpcResponse {
+                    closed_positions: vec![],
+                    remaining_position: None,
+                    status: status as i32,
+                }));
+            }
+        }
+
+        let close_by_result = close_by(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            &request.position_id,
+            &request.opposite_position_id,
+            &request.process_id,
+            my_telemetry,
+        )
+        .await;
+
+        let response = match &close_by_result {
+            Ok(result) => PositionManagerCloseByGrpcResponse {
+                closed_positions: result
+                    .closed_positions
+                    .iter()
+                    .map(|x| x.to_owned().into())
+                    .collect(),
+                remaining_position: result
+                    .remaining_position
+                    .as_ref()
+                    .map(|x| x.to_owned().into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.clone().into();
+                PositionManagerCloseByGrpcResponse {
+                    closed_positions: vec![],
+                    remaining_position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Returning close by grpc response",
+            my_telemetry.clone(),
+            "close_by_result" = &close_by_result,
+            "response" = &response
+        );
+
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn reverse_position(
+        &self,
+        request: tonic::Request<PositionManagerReversePositionGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerReversePositionGrpcResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "ReversePosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Got reverse position request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerReversePositionGrpcResponse {
+                closed_position: None,
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        let reverse_result = reverse_position(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            &request.position_id,
+            &request.process_id,
+            my_telemetry,
+        )
+        .await;
+
+        let response = match &reverse_result {
+            Ok((closed, position)) => PositionManagerReversePositionGrpcResponse {
+                closed_position: Some(closed.to_owned().into()),
+                position: Some(position.to_owned().into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.clone().into();
+                PositionManagerReversePositionGrpcResponse {
+                    closed_position: None,
+                    position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Returning reverse position grpc response",
+            my_telemetry.clone(),
+            "reverse_result" = &reverse_result,
+            "response" = &response
+        );
+
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn partial_close_position(
+        &self,
+        request: tonic::Request<PositionManagerPartialClosePositionGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerPartialClosePositionGrpcResponse>, tonic::Status>
+    {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "PartialClosePosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Got partial close position request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = validate_partial_close_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Partial close position request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(
+                PositionManagerPartialClosePositionGrpcResponse {
+                    closed_position: None,
+                    position: None,
+                    status: status as i32,
+                },
+            ));
+        }
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(
+                PositionManagerPartialClosePositionGrpcResponse {
+                    closed_position: None,
+                    position: None,
+                    status: status as i32,
+                },
+            ));
+        }
+
+        let volume = match request.close_invest_amount {
+            Some(amount) => PartialCloseVolume::InvestAmount(amount),
+            None => PartialCloseVolume::Percent(request.close_percent.unwrap_or_default()),
+        };
+
+        let partial_close_result = partial_close_position(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            &request.position_id,
+            volume,
+            &request.process_id,
+            my_telemetry,
+        )
+        .await;
+
+        let response = match &partial_close_result {
+            Ok((closed, remaining)) => PositionManagerPartialClosePositionGrpcResponse {
+                closed_position: Some(closed.to_owned().into()),
+                position: Some(remaining.to_owned().into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.clone().into();
+                PositionManagerPartialClosePositionGrpcResponse {
+                    closed_position: None,
+                    position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Returning partial close position grpc response",
+            my_telemetry.clone(),
+            "partial_close_result" = &partial_close_result,
+            "response" = &response
+        );
+
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn increase_position(
+        &self,
+        request: tonic::Request<PositionManagerIncreasePositionGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerIncreasePositionGrpcResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "IncreasePosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Got increase position request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = validate_increase_position_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Increase position request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerIncreasePositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerIncreasePositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        let increase_result = increase_position(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            &request.position_id,
+            request.invest_amount,
+            &request.process_id,
+            my_telemetry,
+        )
+        .await;
+
+        let response = match &increase_result {
+            Ok(position) => PositionManagerIncreasePositionGrpcResponse {
+                position: Some(position.to_owned().into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.clone().into();
+                PositionManagerIncreasePositionGrpcResponse {
+                    position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.position_id,
+            "Returning increase position grpc response",
+            my_telemetry.clone(),
+            "increase_result" = &increase_result,
+            "response" = &response
+        );
+
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -173,6 +711,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerTopUpPositionGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "TopUpPosition",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
         trade_log::trade_log!(
             &request.trader_id,
             &request.account_id,
@@ -183,6 +735,37 @@ impl PositionManagerGrpcService for GrpcService {
             "request" = &request
         );
 
+        if let Err(status) = validate_top_up_position_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Top up position request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerTopUpPositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerTopUpPositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let updated_position = {
             let mut active_cache = self.app.active_positions_cache.write().await;
             active_cache.0.update_position(&request.position_id, |x| {
@@ -213,10 +796,11 @@ impl PositionManagerGrpcService for GrpcService {
             };
 
             self.app
-                .active_positions_persistence_publisher
-                .publish(&sb_model, Some(my_telemetry))
-                .await
-                .unwrap();
+                .persistence_outbox
+                .enqueue(OutboxMessage::ActivePosition(sb_model), Some(my_telemetry))
+                .await;
+
+            publish_position_updates(&self.app, &[PositionUpdate::Updated(position)]).await;
         }
 
         trade_log::trade_log!(
@@ -238,10 +822,9 @@ impl PositionManagerGrpcService for GrpcService {
         };
 
         self.app
-            .topping_up_publisher
-            .publish(&topping_up_event, Some(my_telemetry))
-            .await
-            .unwrap();
+            .persistence_outbox
+            .enqueue(OutboxMessage::ToppingUp(topping_up_event), Some(my_telemetry))
+            .await;
 
         let response = match updated_position.clone() {
             Some(position) => PositionManagerTopUpPositionGrpcResponse {
@@ -254,6 +837,7 @@ impl PositionManagerGrpcService for GrpcService {
             },
         };
 
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -264,6 +848,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerUpdateToppingUpGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "UpdateToppingUpSettings",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
         trade_log::trade_log!(
             &request.trader_id,
             &request.account_id,
@@ -274,7 +872,21 @@ impl PositionManagerGrpcService for GrpcService {
             "request" = &request
         );
 
-        let updated_position = {
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerUpdateToppingUpGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        let updated_position = {
             let mut active_cache = self.app.active_positions_cache.write().await;
             active_cache.0.update_position(&request.position_id, |x| {
                 if let Some(src) = x {
@@ -304,10 +916,11 @@ impl PositionManagerGrpcService for GrpcService {
             };
 
             self.app
-                .active_positions_persistence_publisher
-                .publish(&sb_model, Some(my_telemetry))
-                .await
-                .unwrap();
+                .persistence_outbox
+                .enqueue(OutboxMessage::ActivePosition(sb_model), Some(my_telemetry))
+                .await;
+
+            publish_position_updates(&self.app, &[PositionUpdate::Updated(position)]).await;
         };
 
         trade_log::trade_log!(
@@ -331,6 +944,7 @@ impl PositionManagerGrpcService for GrpcService {
             },
         };
 
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -341,12 +955,55 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerChargeSwapGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "ChargeSwap",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        if let Err(status) = validate_charge_swap_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Charge swap request rejected by validation",
+                my_telemetry.clone(),
+                "request" = &request,
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerChargeSwapGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerChargeSwapGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let updated_position = charge_swaps(
             &self.app,
-            &format!(
-                "process-{}",
-                DateTimeAsMicroseconds::now().unix_microseconds
-            ),
+            &request.process_id,
             &request.position_id,
             request.swap_amount,
             my_telemetry,
@@ -376,6 +1033,7 @@ impl PositionManagerGrpcService for GrpcService {
             "response" = &response
         );
 
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -414,6 +1072,53 @@ impl PositionManagerGrpcService for GrpcService {
         request: tonic::Request<PositionManagerUpdateSlTpGrpcRequest>,
     ) -> Result<tonic::Response<PositionManagerUpdateSlTpGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "UpdateSlTp",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        if let Err(status) = validate_update_sl_tp_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.position_id,
+                "Update sl tp request rejected by validation",
+                my_telemetry.clone(),
+                "request" = &request,
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerUpdateSlTpGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        if let Err(status) = self
+            .check_active_position_owner(
+                &request.position_id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerUpdateSlTpGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let updated_position = {
             let mut active_cache = self.app.active_positions_cache.write().await;
             active_cache.0.update_position(&request.position_id, |x| {
@@ -422,6 +1127,26 @@ impl PositionManagerGrpcService for GrpcService {
                     src.base_data.tp_price = request.tp_in_asset_price;
                     src.base_data.sl_profit = request.sl_in_profit;
                     src.base_data.tp_profit = request.tp_in_profit;
+
+                    // Trailing and break-even settings are kept unless the request sets new
+                    // ones or clears them explicitly.
+                    let trailing_stop = TrailingStop::from_request(
+                        request.trailing_stop_distance,
+                        request.trailing_stop_percent,
+                    );
+                    if request.clear_trailing_stop || trailing_stop.is_some() {
+                        set_position_trailing_stop(&mut src.base_data.metadata, trailing_stop);
+                    }
+
+                    let break_even = BreakEvenStop::from_request(
+                        request.break_even_profit,
+                        request.break_even_percent,
+                        request.break_even_offset,
+                    );
+                    if request.clear_break_even_stop || break_even.is_some() {
+                        set_position_break_even_stop(&mut src.base_data.metadata, break_even);
+                    }
+
                     src.base_data.last_update_date = DateTimeAsMicroseconds::now();
                     src.base_data.last_update_process_id = request.process_id.clone();
                     sanitize_sl_tp(&mut src.base_data);
@@ -441,10 +1166,11 @@ impl PositionManagerGrpcService for GrpcService {
             };
 
             self.app
-                .active_positions_persistence_publisher
-                .publish(&sb_model, Some(my_telemetry))
-                .await
-                .unwrap();
+                .persistence_outbox
+                .enqueue(OutboxMessage::ActivePosition(sb_model), Some(my_telemetry))
+                .await;
+
+            publish_position_updates(&self.app, &[PositionUpdate::SlTpChanged(position)]).await;
         }
 
         let response = match updated_position.clone() {
@@ -469,6 +1195,7 @@ impl PositionManagerGrpcService for GrpcService {
             "updated_position" = &updated_position,
             "response" = &response
         );
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -482,6 +1209,21 @@ impl PositionManagerGrpcService for GrpcService {
         request: tonic::Request<PositionManagerCancelPendingGrpcRequest>,
     ) -> Result<tonic::Response<PositionManagerCancelPendingGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
+
+        if let Err(status) = self
+            .check_pending_position_owner(
+                &request.id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerCancelPendingGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let removed = cancel_pending(&self.app, request.clone(), my_telemetry).await;
 
         let response = match removed.clone() {
@@ -513,6 +1255,95 @@ impl PositionManagerGrpcService for GrpcService {
         return Ok(tonic::Response::new(response));
     }
 
+    #[with_telemetry]
+    async fn modify_pending(
+        &self,
+        request: tonic::Request<PositionManagerModifyPendingGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerModifyPendingGrpcResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "ModifyPending",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.id,
+            "Got modify pending request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = validate_modify_pending_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                &request.id,
+                "Modify pending request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerModifyPendingGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        if let Err(status) = self
+            .check_pending_position_owner(&request.id, &request.trader_id, &request.account_id)
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerModifyPendingGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
+        let modified = modify_pending(&self.app, &request, my_telemetry).await;
+
+        let response = match &modified {
+            Ok(position) => PositionManagerModifyPendingGrpcResponse {
+                position: Some(position.to_owned().into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.clone().into();
+                PositionManagerModifyPendingGrpcResponse {
+                    position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            &request.id,
+            "Returning modify pending grpc response",
+            my_telemetry.clone(),
+            "modified" = &modified,
+            "response" = &response
+        );
+
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
     #[with_telemetry]
     async fn open_pending(
         &self,
@@ -520,6 +1351,38 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerOpenPendingGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "OpenPending",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        if let Err(status) = validate_open_pending_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                "",
+                "Open pending request rejected by validation",
+                my_telemetry.clone(),
+                "request" = &request,
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(PositionManagerOpenPendingGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let pending = open_pending(&self.app, request.clone(), my_telemetry).await;
 
         let response = match pending.clone() {
@@ -548,6 +1411,7 @@ impl PositionManagerGrpcService for GrpcService {
             "response" = &response
         );
 
+        idempotency.complete(&response);
         return Ok(tonic::Response::new(response));
     }
 
@@ -589,6 +1453,20 @@ impl PositionManagerGrpcService for GrpcService {
     ) -> Result<tonic::Response<PositionManagerGetPendingPositionGrpcResponse>, tonic::Status> {
         let request = request.into_inner();
 
+        if let Err(status) = self
+            .check_pending_position_owner(
+                &request.id,
+                &request.trader_id,
+                &request.account_id,
+            )
+            .await
+        {
+            return Ok(tonic::Response::new(PositionManagerGetPendingPositionGrpcResponse {
+                position: None,
+                status: status as i32,
+            }));
+        }
+
         let result = {
             let reed = self.app.pending_positions_cache.read().await;
             let result = reed.0.get_by_id(&request.id);
@@ -634,4 +1512,718 @@ impl PositionManagerGrpcService for GrpcService {
 
         return Ok(tonic::Response::new(response));
     }
+
+    #[with_telemetry]
+    async fn reject_pending_execution(
+        &self,
+        request: tonic::Request<PositionManagerRejectPendingExecuteGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerRejectPendingExecuteGrpcResponse>, tonic::Status>
+    {
+        let request = request.into_inner();
+
+        let action = match PositionManagerRejectPendingExecutionAction::try_from(request.action) {
+            Ok(action) => action,
+            Err(_) => {
+                return Ok(tonic::Response::new(
+                    PositionManagerRejectPendingExecuteGrpcResponse {
+                        position: None,
+                        status: PositionManagerOperationsCodes::InvalidRequest as i32,
+                    },
+                ));
+            }
+        };
+
+        let rejected = reject_pending_execution(
+            &self.app,
+            &request.position_id,
+            action.into(),
+            &Uuid::new_v4().to_string(),
+            my_telemetry,
+        )
+        .await;
+
+        let response = match rejected {
+            Ok(position) => PositionManagerRejectPendingExecuteGrpcResponse {
+                position: Some(position.into()),
+                status: PositionManagerOperationsCodes::Ok as i32,
+            },
+            Err(error) => {
+                let grpc_status: PositionManagerOperationsCodes = error.into();
+                PositionManagerRejectPendingExecuteGrpcResponse {
+                    position: None,
+                    status: grpc_status as i32,
+                }
+            }
+        };
+
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn subscribe_account_positions(
+        &self,
+        request: tonic::Request<PositionManagerSubscribeAccountPositionsGrpcRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeAccountPositionsStream>, tonic::Status> {
+        let request = request.into_inner();
+
+        if request.trader_id.is_empty() || request.account_id.is_empty() {
+            return Err(tonic::Status::invalid_argument(
+                "TraderId and AccountId are required",
+            ));
+        }
+
+        let receiver = subscribe_account_positions(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            get_profit_update_interval(request.profit_update_interval_ms),
+        )
+        .await;
+
+        return Ok(tonic::Response::new(Box::pin(PositionUpdatesStream::new(
+            receiver,
+        ))));
+    }
+
+    #[with_telemetry]
+    async fn get_account_summary(
+        &self,
+        request: tonic::Request<PositionManagerGetAccountSummaryGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerGetAccountSummaryGrpcResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let summary =
+            get_account_summary(&self.app, &request.trader_id, &request.account_id).await;
+
+        return Ok(tonic::Response::new(
+            PositionManagerGetAccountSummaryGrpcResponse {
+                status: PositionManagerOperationsCodes::Ok as i32,
+                summary: Some(summary.into()),
+            },
+        ));
+    }
+
+    #[with_telemetry]
+    async fn update_account_balance(
+        &self,
+        request: tonic::Request<PositionManagerUpdateAccountBalanceGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerUpdateAccountBalanceGrpcResponse>, tonic::Status>
+    {
+        let request = request.into_inner();
+
+        let idempotency = match self
+            .app
+            .idempotency_cache
+            .begin(IdempotencyKey::new(
+                &request.trader_id,
+                &request.process_id,
+                "UpdateAccountBalance",
+            ))
+            .await
+        {
+            IdempotencyResult::Replay(response) => return Ok(tonic::Response::new(response)),
+            IdempotencyResult::Execute(guard) => guard,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            "",
+            "Got update account balance request",
+            my_telemetry.clone(),
+            "request" = &request
+        );
+
+        if let Err(status) = validate_update_account_balance_request(&request) {
+            trade_log::trade_log!(
+                &request.trader_id,
+                &request.account_id,
+                &request.process_id,
+                "",
+                "Update account balance request rejected by validation",
+                my_telemetry.clone(),
+                "status" = &status
+            );
+
+            return Ok(tonic::Response::new(
+                PositionManagerUpdateAccountBalanceGrpcResponse {
+                    status: status as i32,
+                },
+            ));
+        }
+
+        let summary = update_account_balance(
+            &self.app,
+            &request.trader_id,
+            &request.account_id,
+            AccountBalance {
+                balance: request.balance,
+                stop_out_level: request.stop_out_level,
+                last_update_process_id: request.process_id.clone(),
+            },
+            &request.applied_close_process_ids,
+        )
+        .await;
+
+        let response = PositionManagerUpdateAccountBalanceGrpcResponse {
+            status: PositionManagerOperationsCodes::Ok as i32,
+        };
+
+        trade_log::trade_log!(
+            &request.trader_id,
+            &request.account_id,
+            &request.process_id,
+            "",
+            "Account balance updated",
+            my_telemetry.clone(),
+            "summary" = &summary
+        );
+
+        idempotency.complete(&response);
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn preview_open_position(
+        &self,
+        request: tonic::Request<PositionManagerOpenPositionGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerPreviewOpenPositionGrpcResponse>, tonic::Status>
+    {
+        let request = request.into_inner();
+
+        if let Err(status) = validate_open_position_request(&request) {
+            return Ok(tonic::Response::new(
+                PositionManagerPreviewOpenPositionGrpcResponse {
+                    status: status as i32,
+                    preview: None,
+                    current_bid_ask: None,
+                },
+            ));
+        }
+
+        let asset_pair = request.asset_pair.clone();
+
+        let response = match preview_open_position(&self.app, request).await {
+            Ok(preview) => PositionManagerPreviewOpenPositionGrpcResponse {
+                status: PositionManagerOperationsCodes::Ok as i32,
+                preview: Some(preview.into()),
+                current_bid_ask: None,
+            },
+            Err(error) => {
+                let current_bid_ask = match error {
+                    EngineError::PriceChanged => self
+                        .app
+                        .active_prices_cache
+                        .read()
+                        .await
+                        .get_by_id(&asset_pair)
+                        .map(|x| x.as_ref().clone().into()),
+                    _ => None,
+                };
+
+                let grpc_status: PositionManagerOperationsCodes = error.into();
+                PositionManagerPreviewOpenPositionGrpcResponse {
+                    status: grpc_status as i32,
+                    preview: None,
+                    current_bid_ask,
+                }
+            }
+        };
+
+        return Ok(tonic::Response::new(response));
+    }
+
+    #[with_telemetry]
+    async fn get_quarantined_ticks(
+        &self,
+        request: tonic::Request<PositionManagerGetQuarantinedTicksGrpcRequest>,
+    ) -> Result<tonic::Response<PositionManagerGetQuarantinedTicksGrpcResponse>, tonic::Status>
+    {
+        let request = request.into_inner();
+
+        let ticks = self
+            .app
+            .tick_filter
+            .lock()
+            .await
+            .get_quarantined()
+            .into_iter()
+            .filter(|x| match &request.asset_pair {
+                Some(asset_pair) => &x.asset_pair == asset_pair,
+                None => true,
+            })
+            .map(|x| x.into())
+            .collect();
+
+        return Ok(tonic::Response::new(
+            PositionManagerGetQuarantinedTicksGrpcResponse { ticks },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        position_manager_grpc::{
+            position_manager_grpc_service_server::PositionManagerGrpcService,
+            PositionManagerCancelPendingGrpcRequest, PositionManagerChargeSwapGrpcRequest,
+            PositionManagerClosePositionGrpcRequest, PositionManagerGetActivePositionGrpcRequest,
+            PositionManagerGetPendingPositionGrpcRequest, PositionManagerModifyPendingGrpcRequest,
+            PositionManagerOpenPendingGrpcRequest,
+            PositionManagerOpenPositionGrpcRequest, PositionManagerOperationsCodes,
+            PositionManagerTopUpPositionGrpcRequest, PositionManagerUpdateSlTpGrpcRequest,
+            PositionManagerUpdateToppingUpGrpcRequest,
+        },
+        test_utils::{create_test_active_position, create_test_app, create_test_pending_position},
+        BreakEvenStop, GrpcService, TrailingStop,
+    };
+
+    const OWNER: &str = "owner";
+    const ACCOUNT: &str = "account";
+    const INTRUDER: &str = "intruder";
+    const ACTIVE_ID: &str = "active";
+    const PENDING_ID: &str = "pending";
+
+    async fn create_service() -> GrpcService {
+        let app = create_test_app();
+
+        app.active_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_active_position(ACTIVE_ID, OWNER, ACCOUNT));
+
+        app.pending_positions_cache
+            .write()
+            .await
+            .0
+            .add_position(create_test_pending_position(PENDING_ID, OWNER, ACCOUNT));
+
+        GrpcService::new(app)
+    }
+
+    fn mismatch() -> i32 {
+        PositionManagerOperationsCodes::PositionOwnershipMismatch as i32
+    }
+
+    #[tokio::test]
+    async fn test_get_active_position_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .get_active_position(tonic::Request::new(
+                PositionManagerGetActivePositionGrpcRequest {
+                    trader_id: INTRUDER.to_string(),
+                    account_id: ACCOUNT.to_string(),
+                    position_id: ACTIVE_ID.to_string(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+        assert!(response.position.is_none());
+
+        let response = service
+            .get_active_position(tonic::Request::new(
+                PositionManagerGetActivePositionGrpcRequest {
+                    trader_id: OWNER.to_string(),
+                    account_id: ACCOUNT.to_string(),
+                    position_id: ACTIVE_ID.to_string(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, PositionManagerOperationsCodes::Ok as i32);
+    }
+
+    #[tokio::test]
+    async fn test_get_pending_position_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .get_pending_position(tonic::Request::new(
+                PositionManagerGetPendingPositionGrpcRequest {
+                    trader_id: OWNER.to_string(),
+                    account_id: "other-account".to_string(),
+                    id: PENDING_ID.to_string(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+        assert!(response.position.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_close_position_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .close_position(tonic::Request::new(PositionManagerClosePositionGrpcRequest {
+                position_id: ACTIVE_ID.to_string(),
+                process_id: "process".to_string(),
+                account_id: ACCOUNT.to_string(),
+                trader_id: INTRUDER.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.active_positions_cache.read().await;
+        assert!(cache.0.get_by_id(ACTIVE_ID).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .cancel_pending(tonic::Request::new(PositionManagerCancelPendingGrpcRequest {
+                id: PENDING_ID.to_string(),
+                trader_id: INTRUDER.to_string(),
+                account_id: ACCOUNT.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.pending_positions_cache.read().await;
+        assert!(cache.0.get_by_id(PENDING_ID).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_modify_pending_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .modify_pending(tonic::Request::new(PositionManagerModifyPendingGrpcRequest {
+                id: PENDING_ID.to_string(),
+                process_id: "process".to_string(),
+                account_id: ACCOUNT.to_string(),
+                trader_id: INTRUDER.to_string(),
+                desire_price: 1.2,
+                invest_amount: 50.0,
+                leverage: 20.0,
+                tp_in_profit: None,
+                sl_in_profit: None,
+                tp_in_asset_price: None,
+                sl_in_asset_price: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.pending_positions_cache.read().await;
+        assert_eq!(
+            cache.0.get_by_id(PENDING_ID).unwrap().base_data.invest_amount,
+            100.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_sl_tp_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .update_sl_tp(tonic::Request::new(PositionManagerUpdateSlTpGrpcRequest {
+                position_id: ACTIVE_ID.to_string(),
+                account_id: ACCOUNT.to_string(),
+                trader_id: INTRUDER.to_string(),
+                tp_in_profit: None,
+                sl_in_profit: None,
+                tp_in_asset_price: None,
+                sl_in_asset_price: Some(0.5),
+                process_id: "process".to_string(),
+                trailing_stop_distance: None,
+                trailing_stop_percent: None,
+                break_even_profit: None,
+                break_even_percent: None,
+                break_even_offset: None,
+                clear_trailing_stop: false,
+                clear_break_even_stop: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.active_positions_cache.read().await;
+        let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+        assert!(position.base_data.sl_price.is_none());
+    }
+
+    fn create_update_sl_tp_request(process_id: &str) -> PositionManagerUpdateSlTpGrpcRequest {
+        PositionManagerUpdateSlTpGrpcRequest {
+            position_id: ACTIVE_ID.to_string(),
+            account_id: ACCOUNT.to_string(),
+            trader_id: OWNER.to_string(),
+            tp_in_profit: None,
+            sl_in_profit: None,
+            tp_in_asset_price: None,
+            sl_in_asset_price: None,
+            process_id: process_id.to_string(),
+            trailing_stop_distance: None,
+            trailing_stop_percent: None,
+            break_even_profit: None,
+            break_even_percent: None,
+            break_even_offset: None,
+            clear_trailing_stop: false,
+            clear_break_even_stop: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_sl_tp_keeps_trailing_stop_unless_cleared() {
+        let service = create_service().await;
+
+        let mut request = create_update_sl_tp_request("set");
+        request.trailing_stop_distance = Some(0.01);
+        request.break_even_profit = Some(5.0);
+        service
+            .update_sl_tp(tonic::Request::new(request))
+            .await
+            .unwrap();
+
+        let mut request = create_update_sl_tp_request("move-sl");
+        request.sl_in_asset_price = Some(1.05);
+        service
+            .update_sl_tp(tonic::Request::new(request))
+            .await
+            .unwrap();
+
+        {
+            let cache = service.app.active_positions_cache.read().await;
+            let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+            let metadata = position.base_data.metadata.as_ref();
+            assert_eq!(
+                TrailingStop::from_metadata(metadata),
+                Some(TrailingStop::Distance(0.01))
+            );
+            assert!(BreakEvenStop::from_metadata(metadata).is_some());
+        }
+
+        let mut request = create_update_sl_tp_request("clear");
+        request.clear_trailing_stop = true;
+        service
+            .update_sl_tp(tonic::Request::new(request))
+            .await
+            .unwrap();
+
+        let cache = service.app.active_positions_cache.read().await;
+        let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+        let metadata = position.base_data.metadata.as_ref();
+        assert!(TrailingStop::from_metadata(metadata).is_none());
+        assert!(BreakEvenStop::from_metadata(metadata).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_top_up_position_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .top_up_position(tonic::Request::new(PositionManagerTopUpPositionGrpcRequest {
+                position_id: ACTIVE_ID.to_string(),
+                account_id: ACCOUNT.to_string(),
+                trader_id: INTRUDER.to_string(),
+                process_id: "process".to_string(),
+                topping_up_amount: 10.0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+        assert_eq!(service.app.persistence_outbox.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_topping_up_settings_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .update_topping_up_settings(tonic::Request::new(
+                PositionManagerUpdateToppingUpGrpcRequest {
+                    position_id: ACTIVE_ID.to_string(),
+                    account_id: ACCOUNT.to_string(),
+                    trader_id: INTRUDER.to_string(),
+                    process_id: "process".to_string(),
+                    is_topping_up: false,
+                    topping_up_percent: None,
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.active_positions_cache.read().await;
+        let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+        assert!(position.base_data.topping_up_percent.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_charge_swap_ownership() {
+        let service = create_service().await;
+
+        let response = service
+            .charge_swap(tonic::Request::new(PositionManagerChargeSwapGrpcRequest {
+                position_id: ACTIVE_ID.to_string(),
+                account_id: ACCOUNT.to_string(),
+                trader_id: INTRUDER.to_string(),
+                process_id: "process".to_string(),
+                swap_amount: 5.0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.status, mismatch());
+
+        let cache = service.app.active_positions_cache.read().await;
+        let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+        assert!(position.state.swaps.swaps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_charge_swap_retry_is_replayed() {
+        let service = create_service().await;
+
+        let request = PositionManagerChargeSwapGrpcRequest {
+            position_id: ACTIVE_ID.to_string(),
+            account_id: ACCOUNT.to_string(),
+            trader_id: OWNER.to_string(),
+            process_id: "swap-process".to_string(),
+            swap_amount: 5.0,
+        };
+
+        for _ in 0..2 {
+            let response = service
+                .charge_swap(tonic::Request::new(request.clone()))
+                .await
+                .unwrap()
+                .into_inner();
+
+            assert_eq!(response.status, PositionManagerOperationsCodes::Ok as i32);
+        }
+
+        let cache = service.app.active_positions_cache.read().await;
+        let position = cache.0.get_by_id(ACTIVE_ID).unwrap();
+        assert_eq!(position.state.swaps.swaps.len(), 1);
+        assert_eq!(position.base_data.last_update_process_id, "swap-process");
+    }
+
+    #[tokio::test]
+    async fn test_open_position_rejects_taken_id() {
+        let service = create_service().await;
+
+        let response = service
+            .open_position(tonic::Request::new(PositionManagerOpenPositionGrpcRequest {
+                asset_pair: "EURUSD".to_string(),
+                side: 0,
+                invest_amount: 100.0,
+                leverage: 10.0,
+                stop_out_percent: 90.0,
+                process_id: "open-process".to_string(),
+                tp_in_profit: None,
+                sl_in_profit: None,
+                tp_in_asset_price: None,
+                sl_in_asset_price: None,
+                open_price: None,
+                open_bid_ask: None,
+                account_id: ACCOUNT.to_string(),
+                trader_id: OWNER.to_string(),
+                base: "EUR".to_string(),
+                quote: "USD".to_string(),
+                collateral_currency: "USD".to_string(),
+                id: Some(PENDING_ID.to_string()),
+                open_process_id: None,
+                metadata: HashMap::new(),
+                topping_up_percent: None,
+                margin_call_percent: None,
+                trailing_stop_distance: None,
+                trailing_stop_percent: None,
+                break_even_profit: None,
+                break_even_percent: None,
+                break_even_offset: None,
+                max_slippage: None,
+                max_slippage_points: None,
+                point_size: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            response.status,
+            PositionManagerOperationsCodes::PositionAlreadyExists as i32
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_pending_rejects_taken_id() {
+        let service = create_service().await;
+
+        let response = service
+            .open_pending(tonic::Request::new(PositionManagerOpenPendingGrpcRequest {
+                asset_pair: "EURUSD".to_string(),
+                side: 0,
+                invest_amount: 100.0,
+                leverage: 10.0,
+                stop_out_percent: 90.0,
+                process_id: "open-pending-process".to_string(),
+                tp_in_profit: None,
+                sl_in_profit: None,
+                tp_in_asset_price: None,
+                sl_in_asset_price: None,
+                open_price: None,
+                open_bid_ask: None,
+                account_id: ACCOUNT.to_string(),
+                trader_id: OWNER.to_string(),
+                base: "EUR".to_string(),
+                quote: "USD".to_string(),
+                collateral_currency: "USD".to_string(),
+                id: Some(ACTIVE_ID.to_string()),
+                desire_price: 1.0,
+                open_process_id: None,
+                metadata: HashMap::new(),
+                topping_up_percent: None,
+                margin_call_percent: None,
+                trailing_stop_distance: None,
+                trailing_stop_percent: None,
+                break_even_profit: None,
+                break_even_percent: None,
+                break_even_offset: None,
+                expire_at_unix_timestamp_milis: None,
+                group_id: None,
+                group_role: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            response.status,
+            PositionManagerOperationsCodes::PositionAlreadyExists as i32
+        );
+
+        let cache = service.app.active_positions_cache.read().await;
+        assert!(cache.0.get_by_id(ACTIVE_ID).is_some());
+    }
 }
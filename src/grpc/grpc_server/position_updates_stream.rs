@@ -0,0 +1,28 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use service_sdk::futures_core;
+use tokio::sync::mpsc;
+
+use crate::position_manager_grpc::PositionManagerPositionUpdateGrpcModel;
+
+// Ends when the hub drops the subscriber, the client is expected to subscribe again.
+pub struct PositionUpdatesStream {
+    receiver: mpsc::Receiver<PositionManagerPositionUpdateGrpcModel>,
+}
+
+impl PositionUpdatesStream {
+    pub fn new(receiver: mpsc::Receiver<PositionManagerPositionUpdateGrpcModel>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl futures_core::Stream for PositionUpdatesStream {
+    type Item = Result<PositionManagerPositionUpdateGrpcModel, tonic::Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        return self.receiver.poll_recv(cx).map(|x| x.map(Ok));
+    }
+}
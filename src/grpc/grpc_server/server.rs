@@ -1,6 +1,6 @@
-
-use crate::AppContext;
+use crate::{position_manager_grpc::PositionManagerOperationsCodes, AppContext};
 use std::sync::Arc;
+use trading_sdk::mt_engine::MtPositionBaseData;
 
 #[derive(Clone)]
 pub struct GrpcService {
@@ -11,4 +11,46 @@ impl GrpcService {
     pub fn new(app: Arc<AppContext>) -> Self {
         Self { app }
     }
+
+    pub async fn check_active_position_owner(
+        &self,
+        position_id: &str,
+        trader_id: &str,
+        account_id: &str,
+    ) -> Result<(), PositionManagerOperationsCodes> {
+        let reed = self.app.active_positions_cache.read().await;
+        let position = reed
+            .0
+            .get_by_id(position_id)
+            .ok_or(PositionManagerOperationsCodes::PositionNotFound)?;
+
+        return check_position_owner(&position.base_data, trader_id, account_id);
+    }
+
+    pub async fn check_pending_position_owner(
+        &self,
+        position_id: &str,
+        trader_id: &str,
+        account_id: &str,
+    ) -> Result<(), PositionManagerOperationsCodes> {
+        let reed = self.app.pending_positions_cache.read().await;
+        let position = reed
+            .0
+            .get_by_id(position_id)
+            .ok_or(PositionManagerOperationsCodes::PositionNotFound)?;
+
+        return check_position_owner(&position.base_data, trader_id, account_id);
+    }
+}
+
+fn check_position_owner(
+    base_data: &MtPositionBaseData,
+    trader_id: &str,
+    account_id: &str,
+) -> Result<(), PositionManagerOperationsCodes> {
+    if base_data.trader_id != trader_id || base_data.account_id != account_id {
+        return Err(PositionManagerOperationsCodes::PositionOwnershipMismatch);
+    }
+
+    return Ok(());
 }
\ No newline at end of file
@@ -9,13 +9,16 @@ use trading_sdk::mt_engine::{
 };
 
 use crate::{
+    calculate_trigger_prices,
     position_manager_grpc::{
-        PositionManagerActivePositionGrpcModel, PositionManagerBidAsk,
-        PositionManagerClosePositionReason, PositionManagerClosedPositionGrpcModel,
-        PositionManagerOperationsCodes, PositionManagerPendingPositionGrpcModel,
-        PositionManagerPositionSide, PositionManagerSwapGrpcModel,
+        PositionManagerAccountSummaryGrpcModel, PositionManagerActivePositionGrpcModel,
+        PositionManagerBidAsk, PositionManagerClosePositionReason,
+        PositionManagerClosedPositionGrpcModel, PositionManagerInstrumentExposureGrpcModel,
+        PositionManagerOpenPositionPreviewGrpcModel, PositionManagerOperationsCodes,
+        PositionManagerPendingPositionGrpcModel, PositionManagerPositionSide,
+        PositionManagerQuarantinedTickGrpcModel, PositionManagerSwapGrpcModel,
     },
-    EngineError,
+    AccountSummary, EngineError, InstrumentExposure, OpenPositionPreview, QuarantinedTick,
 };
 
 impl Into<PositionManagerPositionSide> for MtPositionSide {
@@ -50,6 +53,8 @@ impl Into<PositionManagerSwapGrpcModel> for MtPositionSwap {
 impl Into<PositionManagerActivePositionGrpcModel> for MtPosition<MtPositionActiveState> {
     fn into(self) -> PositionManagerActivePositionGrpcModel {
         let side: PositionManagerPositionSide = self.base_data.side.into();
+        // Computed from the current quote-collateral price, so they follow the price updates.
+        let trigger_prices = calculate_trigger_prices(&self);
 
         PositionManagerActivePositionGrpcModel {
             id: self.base_data.id,
@@ -88,6 +93,80 @@ impl Into<PositionManagerActivePositionGrpcModel> for MtPosition<MtPositionActiv
             topping_up_percent: self.base_data.topping_up_percent,
             margin_call_percent: self.base_data.margin_call_percent,
             reserved_fund_for_topping_up: self.state.topping_up,
+            stop_out_price: trigger_prices.stop_out_price,
+            margin_call_price: trigger_prices.margin_call_price,
+            topping_up_price: trigger_prices.topping_up_price,
+        }
+    }
+}
+
+impl Into<PositionManagerQuarantinedTickGrpcModel> for QuarantinedTick {
+    fn into(self) -> PositionManagerQuarantinedTickGrpcModel {
+        PositionManagerQuarantinedTickGrpcModel {
+            asset_pair: self.asset_pair,
+            bid: self.bid,
+            ask: self.ask,
+            date_time_unix_timestamp_milis: self.date_time_unix_milis,
+            reason: self.reason.as_str().to_string(),
+        }
+    }
+}
+
+impl Into<PositionManagerOpenPositionPreviewGrpcModel> for OpenPositionPreview {
+    fn into(self) -> PositionManagerOpenPositionPreviewGrpcModel {
+        let side: PositionManagerPositionSide = self.position.base_data.side.into();
+
+        PositionManagerOpenPositionPreviewGrpcModel {
+            asset_pair: self.position.base_data.asset_pair,
+            side: side as i32,
+            open_price: self.position.state.open_data.asset_open_price,
+            open_bid_ask: Some(self.position.state.open_data.asset_open_bid_ask.into()),
+            collateral: self.position.base_data.collateral,
+            required_margin: self.required_margin,
+            base_collateral_open_price: self.position.state.open_data.base_collateral_open_price,
+            quote_collateral_price: self.position.state.quote_collateral_active_price,
+            tp_in_profit: self.position.base_data.tp_profit,
+            sl_in_profit: self.position.base_data.sl_profit,
+            tp_in_asset_price: self.position.base_data.tp_price,
+            sl_in_asset_price: self.position.base_data.sl_price,
+            stop_out_price: self.trigger_prices.stop_out_price,
+            margin_call_price: self.trigger_prices.margin_call_price,
+            topping_up_price: self.trigger_prices.topping_up_price,
+        }
+    }
+}
+
+impl Into<PositionManagerInstrumentExposureGrpcModel> for InstrumentExposure {
+    fn into(self) -> PositionManagerInstrumentExposureGrpcModel {
+        PositionManagerInstrumentExposureGrpcModel {
+            asset_pair: self.asset_pair,
+            net_exposure: self.net_exposure,
+            pending_exposure: self.pending_exposure,
+            active_positions_count: self.active_positions_count as i32,
+            pending_positions_count: self.pending_positions_count as i32,
+        }
+    }
+}
+
+impl Into<PositionManagerAccountSummaryGrpcModel> for AccountSummary {
+    fn into(self) -> PositionManagerAccountSummaryGrpcModel {
+        PositionManagerAccountSummaryGrpcModel {
+            trader_id: self.trader_id,
+            account_id: self.account_id,
+            profit: self.profit,
+            invest_amount: self.invest_amount,
+            reserved_fund_for_topping_up: self.reserved_topping_up,
+            swaps: self.swaps,
+            active_positions_count: self.active_positions_count as i32,
+            pending_positions_count: self.pending_positions_count as i32,
+            pending_invest_amount: self.pending_invest_amount,
+            exposures: self.exposures.into_iter().map(|x| x.into()).collect(),
+            balance: self.margin.as_ref().map(|x| x.balance),
+            equity: self.margin.as_ref().map(|x| x.equity),
+            used_margin: self.margin.as_ref().map(|x| x.used_margin),
+            free_margin: self.margin.as_ref().map(|x| x.free_margin),
+            margin_level: self.margin.as_ref().and_then(|x| x.margin_level),
+            balance_missing: self.margin.is_none(),
         }
     }
 }
@@ -102,6 +181,7 @@ impl Into<PositionManagerClosePositionReason> for MtPositionCloseReason {
             MtPositionCloseReason::TakeProfit => PositionManagerClosePositionReason::TakeProfit,
             MtPositionCloseReason::StopLoss => PositionManagerClosePositionReason::StopLoss,
             MtPositionCloseReason::ForceClose => PositionManagerClosePositionReason::ForceClose,
+            MtPositionCloseReason::CloseBy => PositionManagerClosePositionReason::CloseBy,
         }
     }
 }
@@ -215,6 +295,7 @@ pub fn map_closed_to_sb(src: &MtPosition<MtPositionClosedState>) -> OrderSbModel
         MtPositionCloseReason::TakeProfit => OrderCloseReasonSbModel::TakeProfit,
         MtPositionCloseReason::StopLoss => OrderCloseReasonSbModel::StopLoss,
         MtPositionCloseReason::ForceClose => OrderCloseReasonSbModel::ForceClose,
+        MtPositionCloseReason::CloseBy => OrderCloseReasonSbModel::CloseBy,
     };
 
     OrderSbModel {
@@ -273,6 +354,14 @@ impl Into<PositionManagerOperationsCodes> for EngineError {
         match self {
             EngineError::NoLiquidity => PositionManagerOperationsCodes::NoLiquidity,
             EngineError::PositionNotFound => PositionManagerOperationsCodes::PositionNotFound,
+            EngineError::PositionAlreadyExists => {
+                PositionManagerOperationsCodes::PositionAlreadyExists
+            }
+            EngineError::InvalidAmount => PositionManagerOperationsCodes::InvalidAmount,
+            EngineError::CloseByMismatch => PositionManagerOperationsCodes::CloseByMismatch,
+            EngineError::PriceChanged => PositionManagerOperationsCodes::PriceChanged,
+            EngineError::StalePrice => PositionManagerOperationsCodes::StalePrice,
+            EngineError::LeverageMismatch => PositionManagerOperationsCodes::LeverageMismatch,
         }
     }
 }
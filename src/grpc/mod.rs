@@ -1,7 +1,9 @@
 mod grpc_server;
 mod client;
 mod mappers;
+mod validation;
 
 pub use grpc_server::*;
 pub use client::*;
-pub use mappers::*;
\ No newline at end of file
+pub use mappers::*;
+pub use validation::*;
\ No newline at end of file
@@ -0,0 +1,548 @@
+use service_sdk::rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::position_manager_grpc::{
+    PositionManagerChargeSwapGrpcRequest, PositionManagerCloseByGrpcRequest,
+    PositionManagerIncreasePositionGrpcRequest, PositionManagerModifyPendingGrpcRequest,
+    PositionManagerOpenPendingGrpcRequest, PositionManagerOpenPositionGrpcRequest,
+    PositionManagerOperationsCodes, PositionManagerPartialClosePositionGrpcRequest,
+    PositionManagerPendingGroupRole, PositionManagerPositionSide,
+    PositionManagerTopUpPositionGrpcRequest, PositionManagerUpdateAccountBalanceGrpcRequest,
+    PositionManagerUpdateSlTpGrpcRequest,
+};
+
+pub fn validate_open_position_request(
+    request: &PositionManagerOpenPositionGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+    validate_side(request.side)?;
+    validate_instrument(&request.asset_pair, &request.collateral_currency)?;
+    validate_volume(
+        request.invest_amount,
+        request.leverage,
+        request.stop_out_percent,
+    )?;
+    validate_sl_tp(
+        request.sl_in_profit,
+        request.tp_in_profit,
+        request.sl_in_asset_price,
+        request.tp_in_asset_price,
+    )?;
+    validate_optional_price(request.open_price)?;
+    validate_optional_percent(request.topping_up_percent)?;
+    validate_optional_percent(request.margin_call_percent)?;
+
+    validate_trailing_stop(
+        request.trailing_stop_distance,
+        request.trailing_stop_percent,
+    )?;
+    validate_break_even(
+        request.break_even_profit,
+        request.break_even_percent,
+        request.break_even_offset,
+    )?;
+
+    validate_max_slippage(request)?;
+
+    return Ok(());
+}
+
+pub fn validate_open_pending_request(
+    request: &PositionManagerOpenPendingGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+    validate_side(request.side)?;
+    validate_instrument(&request.asset_pair, &request.collateral_currency)?;
+    validate_volume(
+        request.invest_amount,
+        request.leverage,
+        request.stop_out_percent,
+    )?;
+    validate_sl_tp(
+        request.sl_in_profit,
+        request.tp_in_profit,
+        request.sl_in_asset_price,
+        request.tp_in_asset_price,
+    )?;
+    validate_price(request.desire_price)?;
+    validate_optional_price(request.open_price)?;
+    validate_optional_percent(request.topping_up_percent)?;
+    validate_optional_percent(request.margin_call_percent)?;
+
+    validate_trailing_stop(
+        request.trailing_stop_distance,
+        request.trailing_stop_percent,
+    )?;
+    validate_break_even(
+        request.break_even_profit,
+        request.break_even_percent,
+        request.break_even_offset,
+    )?;
+
+    validate_expiry(request.expire_at_unix_timestamp_milis)?;
+    validate_pending_group(&request.group_id, request.group_role)?;
+
+    return Ok(());
+}
+
+pub fn validate_modify_pending_request(
+    request: &PositionManagerModifyPendingGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if !request.invest_amount.is_finite() || request.invest_amount <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidInvestAmount);
+    }
+
+    if !request.leverage.is_finite() || request.leverage <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidLeverage);
+    }
+
+    validate_sl_tp(
+        request.sl_in_profit,
+        request.tp_in_profit,
+        request.sl_in_asset_price,
+        request.tp_in_asset_price,
+    )?;
+    validate_price(request.desire_price)?;
+
+    return Ok(());
+}
+
+pub fn validate_update_sl_tp_request(
+    request: &PositionManagerUpdateSlTpGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+    validate_sl_tp(
+        request.sl_in_profit,
+        request.tp_in_profit,
+        request.sl_in_asset_price,
+        request.tp_in_asset_price,
+    )?;
+
+    validate_trailing_stop(
+        request.trailing_stop_distance,
+        request.trailing_stop_percent,
+    )?;
+    validate_break_even(
+        request.break_even_profit,
+        request.break_even_percent,
+        request.break_even_offset,
+    )?;
+
+    return Ok(());
+}
+
+pub fn validate_charge_swap_request(
+    request: &PositionManagerChargeSwapGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if !request.swap_amount.is_finite() {
+        return Err(PositionManagerOperationsCodes::InvalidAmount);
+    }
+
+    return Ok(());
+}
+
+pub fn validate_top_up_position_request(
+    request: &PositionManagerTopUpPositionGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if !request.topping_up_amount.is_finite() || request.topping_up_amount <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidAmount);
+    }
+
+    return Ok(());
+}
+
+pub fn validate_partial_close_request(
+    request: &PositionManagerPartialClosePositionGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    match (request.close_invest_amount, request.close_percent) {
+        (Some(amount), None) => {
+            if !amount.is_finite() || amount <= 0.0 {
+                return Err(PositionManagerOperationsCodes::InvalidAmount);
+            }
+        }
+        (None, Some(percent)) => {
+            if !percent.is_finite() || percent <= 0.0 || percent >= 100.0 {
+                return Err(PositionManagerOperationsCodes::InvalidPercent);
+            }
+        }
+        _ => return Err(PositionManagerOperationsCodes::InvalidRequest),
+    }
+
+    return Ok(());
+}
+
+pub fn validate_increase_position_request(
+    request: &PositionManagerIncreasePositionGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if !request.invest_amount.is_finite() || request.invest_amount <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidInvestAmount);
+    }
+
+    return Ok(());
+}
+
+pub fn validate_close_by_request(
+    request: &PositionManagerCloseByGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if request.position_id.is_empty() || request.opposite_position_id.is_empty() {
+        return Err(PositionManagerOperationsCodes::InvalidRequest);
+    }
+
+    return Ok(());
+}
+
+pub fn validate_update_account_balance_request(
+    request: &PositionManagerUpdateAccountBalanceGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    validate_owner(&request.trader_id, &request.account_id)?;
+
+    if !request.balance.is_finite() {
+        return Err(PositionManagerOperationsCodes::InvalidAmount);
+    }
+
+    // The stop out level is a margin level, it is not limited to 100 percent.
+    if let Some(stop_out_level) = request.stop_out_level {
+        if !stop_out_level.is_finite() || stop_out_level < 0.0 {
+            return Err(PositionManagerOperationsCodes::InvalidStopOutPercent);
+        }
+    }
+
+    return Ok(());
+}
+
+fn validate_owner(trader_id: &str, account_id: &str) -> Result<(), PositionManagerOperationsCodes> {
+    if trader_id.is_empty() || account_id.is_empty() {
+        return Err(PositionManagerOperationsCodes::InvalidRequest);
+    }
+
+    return Ok(());
+}
+
+fn validate_side(side: i32) -> Result<(), PositionManagerOperationsCodes> {
+    if PositionManagerPositionSide::try_from(side).is_err() {
+        return Err(PositionManagerOperationsCodes::InvalidSide);
+    }
+
+    return Ok(());
+}
+
+fn validate_instrument(
+    asset_pair: &str,
+    collateral_currency: &str,
+) -> Result<(), PositionManagerOperationsCodes> {
+    if asset_pair.is_empty() {
+        return Err(PositionManagerOperationsCodes::InvalidAssetPair);
+    }
+
+    if collateral_currency.is_empty() {
+        return Err(PositionManagerOperationsCodes::InvalidCollateralCurrency);
+    }
+
+    return Ok(());
+}
+
+fn validate_volume(
+    invest_amount: f64,
+    leverage: f64,
+    stop_out_percent: f64,
+) -> Result<(), PositionManagerOperationsCodes> {
+    if !invest_amount.is_finite() || invest_amount <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidInvestAmount);
+    }
+
+    if !leverage.is_finite() || leverage <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidLeverage);
+    }
+
+    if !stop_out_percent.is_finite() || !(0.0..=100.0).contains(&stop_out_percent) {
+        return Err(PositionManagerOperationsCodes::InvalidStopOutPercent);
+    }
+
+    return Ok(());
+}
+
+fn validate_sl_tp(
+    sl_profit: Option<f64>,
+    tp_profit: Option<f64>,
+    sl_price: Option<f64>,
+    tp_price: Option<f64>,
+) -> Result<(), PositionManagerOperationsCodes> {
+    for profit in [sl_profit, tp_profit].into_iter().flatten() {
+        if !profit.is_finite() {
+            return Err(PositionManagerOperationsCodes::InvalidSlTp);
+        }
+    }
+
+    for price in [sl_price, tp_price].into_iter().flatten() {
+        if !price.is_finite() || price <= 0.0 {
+            return Err(PositionManagerOperationsCodes::InvalidSlTp);
+        }
+    }
+
+    return Ok(());
+}
+
+fn validate_trailing_stop(
+    distance: Option<f64>,
+    percent: Option<f64>,
+) -> Result<(), PositionManagerOperationsCodes> {
+    match (distance, percent) {
+        (Some(_), Some(_)) => return Err(PositionManagerOperationsCodes::InvalidTrailingStop),
+        (Some(distance), None) => {
+            if !distance.is_finite() || distance <= 0.0 {
+                return Err(PositionManagerOperationsCodes::InvalidTrailingStop);
+            }
+        }
+        (None, Some(percent)) => {
+            if !percent.is_finite() || percent <= 0.0 || percent >= 100.0 {
+                return Err(PositionManagerOperationsCodes::InvalidTrailingStop);
+            }
+        }
+        (None, None) => {}
+    }
+
+    return Ok(());
+}
+
+fn validate_break_even(
+    profit: Option<f64>,
+    percent: Option<f64>,
+    offset: Option<f64>,
+) -> Result<(), PositionManagerOperationsCodes> {
+    match (profit, percent) {
+        (Some(_), Some(_)) => return Err(PositionManagerOperationsCodes::InvalidBreakEven),
+        (Some(value), None) | (None, Some(value)) => {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(PositionManagerOperationsCodes::InvalidBreakEven);
+            }
+        }
+        (None, None) => {
+            if offset.is_some() {
+                return Err(PositionManagerOperationsCodes::InvalidBreakEven);
+            }
+        }
+    }
+
+    if let Some(offset) = offset {
+        if !offset.is_finite() || offset < 0.0 {
+            return Err(PositionManagerOperationsCodes::InvalidBreakEven);
+        }
+    }
+
+    return Ok(());
+}
+
+fn validate_max_slippage(
+    request: &PositionManagerOpenPositionGrpcRequest,
+) -> Result<(), PositionManagerOperationsCodes> {
+    if request.max_slippage.is_none() && request.max_slippage_points.is_none() {
+        return Ok(());
+    }
+
+    if request.open_price.is_none() && request.open_bid_ask.is_none() {
+        return Err(PositionManagerOperationsCodes::InvalidRequest);
+    }
+
+    if let Some(max_slippage) = request.max_slippage {
+        if !max_slippage.is_finite() || max_slippage < 0.0 {
+            return Err(PositionManagerOperationsCodes::InvalidRequest);
+        }
+    }
+
+    if let Some(points) = request.max_slippage_points {
+        let point_size = request.point_size.unwrap_or(0.0);
+
+        if !points.is_finite() || points < 0.0 || !point_size.is_finite() || point_size <= 0.0 {
+            return Err(PositionManagerOperationsCodes::InvalidRequest);
+        }
+    }
+
+    return Ok(());
+}
+
+fn validate_expiry(expire_at: Option<u64>) -> Result<(), PositionManagerOperationsCodes> {
+    if let Some(expire_at) = expire_at {
+        if expire_at as i64 <= DateTimeAsMicroseconds::now().unix_microseconds {
+            return Err(PositionManagerOperationsCodes::InvalidExpiry);
+        }
+    }
+
+    return Ok(());
+}
+
+fn validate_pending_group(
+    group_id: &Option<String>,
+    group_role: Option<i32>,
+) -> Result<(), PositionManagerOperationsCodes> {
+    match (group_id, group_role) {
+        (None, None) => {}
+        (Some(group_id), Some(group_role)) => {
+            if group_id.is_empty() || PositionManagerPendingGroupRole::try_from(group_role).is_err()
+            {
+                return Err(PositionManagerOperationsCodes::InvalidRequest);
+            }
+        }
+        _ => return Err(PositionManagerOperationsCodes::InvalidRequest),
+    }
+
+    return Ok(());
+}
+
+fn validate_price(price: f64) -> Result<(), PositionManagerOperationsCodes> {
+    if !price.is_finite() || price <= 0.0 {
+        return Err(PositionManagerOperationsCodes::InvalidPrice);
+    }
+
+    return Ok(());
+}
+
+fn validate_optional_price(price: Option<f64>) -> Result<(), PositionManagerOperationsCodes> {
+    match price {
+        Some(price) => validate_price(price),
+        None => Ok(()),
+    }
+}
+
+fn validate_optional_percent(percent: Option<f64>) -> Result<(), PositionManagerOperationsCodes> {
+    if let Some(percent) = percent {
+        if !percent.is_finite() || !(0.0..=100.0).contains(&percent) {
+            return Err(PositionManagerOperationsCodes::InvalidPercent);
+        }
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::position_manager_grpc::{
+        PositionManagerOpenPositionGrpcRequest, PositionManagerOperationsCodes,
+    };
+
+    use super::validate_open_position_request;
+
+    fn create_request() -> PositionManagerOpenPositionGrpcRequest {
+        PositionManagerOpenPositionGrpcRequest {
+            asset_pair: "EURUSD".to_string(),
+            side: 0,
+            invest_amount: 100.0,
+            leverage: 10.0,
+            stop_out_percent: 90.0,
+            process_id: "process".to_string(),
+            tp_in_profit: None,
+            sl_in_profit: None,
+            tp_in_asset_price: None,
+            sl_in_asset_price: None,
+            open_price: None,
+            open_bid_ask: None,
+            account_id: "account".to_string(),
+            trader_id: "trader".to_string(),
+            base: "EUR".to_string(),
+            quote: "USD".to_string(),
+            collateral_currency: "USD".to_string(),
+            id: None,
+            open_process_id: None,
+            metadata: HashMap::new(),
+            topping_up_percent: None,
+            margin_call_percent: None,
+            trailing_stop_distance: None,
+            trailing_stop_percent: None,
+            break_even_profit: None,
+            break_even_percent: None,
+            break_even_offset: None,
+            max_slippage: None,
+            max_slippage_points: None,
+            point_size: None,
+        }
+    }
+
+    #[test]
+    fn test_open_position_validation() {
+        assert_eq!(validate_open_position_request(&create_request()), Ok(()));
+
+        let mut request = create_request();
+        request.side = 5;
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidSide)
+        );
+
+        let mut request = create_request();
+        request.invest_amount = 0.0;
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidInvestAmount)
+        );
+
+        let mut request = create_request();
+        request.leverage = -1.0;
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidLeverage)
+        );
+
+        let mut request = create_request();
+        request.stop_out_percent = 120.0;
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidStopOutPercent)
+        );
+
+        let mut request = create_request();
+        request.sl_in_asset_price = Some(f64::NAN);
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidSlTp)
+        );
+
+        let mut request = create_request();
+        request.asset_pair = "".to_string();
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidAssetPair)
+        );
+
+        let mut request = create_request();
+        request.collateral_currency = "".to_string();
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidCollateralCurrency)
+        );
+
+        let mut request = create_request();
+        request.trailing_stop_distance = Some(0.01);
+        request.trailing_stop_percent = Some(1.0);
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidTrailingStop)
+        );
+
+        let mut request = create_request();
+        request.break_even_offset = Some(0.001);
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidBreakEven)
+        );
+
+        let mut request = create_request();
+        request.max_slippage_points = Some(5.0);
+        request.open_price = Some(1.1);
+        assert_eq!(
+            validate_open_position_request(&request),
+            Err(PositionManagerOperationsCodes::InvalidRequest)
+        );
+    }
+}
@@ -1,14 +1,20 @@
 mod app_context;
 mod bg;
+mod caches;
 mod flows;
 mod grpc;
+mod outbox;
 mod settings;
 mod utils;
+#[cfg(test)]
+mod test_utils;
 
 pub use app_context::*;
 pub use bg::*;
+pub use caches::*;
 pub use flows::*;
 pub use grpc::*;
+pub use outbox::*;
 pub use settings::*;
 
 use serde::{Deserialize, Serialize};
@@ -24,4 +30,21 @@ pub mod position_manager_grpc {
 pub enum EngineError {
     NoLiquidity,
     PositionNotFound,
+    PositionAlreadyExists,
+    InvalidAmount,
+    CloseByMismatch,
+    PriceChanged,
+    StalePrice,
+    LeverageMismatch,
+}
+
+impl From<trading_sdk::mt_engine::MtEngineError> for EngineError {
+    fn from(src: trading_sdk::mt_engine::MtEngineError) -> Self {
+        match src {
+            trading_sdk::mt_engine::MtEngineError::NoLiquidity => EngineError::NoLiquidity,
+            trading_sdk::mt_engine::MtEngineError::PositionNotFound => {
+                EngineError::PositionNotFound
+            }
+        }
+    }
 }
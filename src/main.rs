@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use position_manager::{
     position_manager_grpc::position_manager_grpc_service_server::PositionManagerGrpcServiceServer,
-    AppContext, GrpcService, PricesListener, SettingsReader,
+    republish_awaiting_confirmations, run_outbox_sender, run_pending_expiry_timer, run_stale_prices_monitor, AppContext, GrpcService, PricesListener, SettingsReader,
 };
 use service_sdk::ServiceInfo;
 
@@ -13,6 +13,10 @@ async fn main() {
 
     let mut service_context = service_sdk::ServiceContext::new(settings_reader.clone()).await;
     let app_context = Arc::new(AppContext::new(&settings_reader, &service_context).await);
+    tokio::spawn(run_outbox_sender(app_context.clone()));
+    tokio::spawn(run_pending_expiry_timer(app_context.clone()));
+    tokio::spawn(run_stale_prices_monitor(app_context.clone()));
+    tokio::spawn(republish_awaiting_confirmations(app_context.clone()));
     service_context.configure_grpc_server(|builder| {
         builder.add_grpc_service(PositionManagerGrpcServiceServer::new(GrpcService::new(
             app_context.clone(),
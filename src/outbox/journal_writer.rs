@@ -0,0 +1,266 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use tokio::sync::oneshot;
+
+const ACK_RECORD_KIND: u8 = 0;
+const RECORD_HEADER_SIZE: usize = 13;
+const COMPACT_JOURNAL_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct JournalRecord {
+    pub id: u64,
+    pub kind: u8,
+    pub payload: Vec<u8>,
+}
+
+enum JournalCommand {
+    Append {
+        record: JournalRecord,
+        written: oneshot::Sender<()>,
+    },
+    Ack(u64),
+}
+
+// The journal is written from its own thread so a slow disk never blocks the runtime.
+// Everything sent while the previous batch was syncing goes out with one write and one sync.
+pub struct JournalWriter {
+    commands: Option<Sender<JournalCommand>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl JournalWriter {
+    pub fn new(path: &str, records: &[JournalRecord]) -> Self {
+        Self::start(path, records, COMPACT_JOURNAL_SIZE)
+    }
+
+    fn start(path: &str, records: &[JournalRecord], compact_size: usize) -> Self {
+        let journal = rewrite_journal(path, records).unwrap();
+        let state = JournalState {
+            path: path.to_string(),
+            journal,
+            live: records.iter().map(|x| (x.id, x.clone())).collect(),
+            live_size: records.iter().map(get_record_size).sum(),
+            size: records.iter().map(get_record_size).sum(),
+            compact_size,
+        };
+        let (commands, receiver) = channel();
+
+        let thread = std::thread::Builder::new()
+            .name("outbox-journal".to_string())
+            .spawn(move || run_writer(state, receiver))
+            .unwrap();
+
+        Self {
+            commands: Some(commands),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn append(&self, record: JournalRecord) -> oneshot::Receiver<()> {
+        let (written, result) = oneshot::channel();
+        self.send(JournalCommand::Append { record, written });
+        return result;
+    }
+
+    pub fn ack(&self, id: u64) {
+        self.send(JournalCommand::Ack(id));
+    }
+
+    fn send(&self, command: JournalCommand) {
+        if let Some(commands) = self.commands.as_ref() {
+            if commands.send(command).is_err() {
+                service_sdk::metrics::counter!("outbox_journal_errors").increment(1);
+                println!("Outbox journal writer is stopped");
+            }
+        }
+    }
+}
+
+impl Drop for JournalWriter {
+    fn drop(&mut self) {
+        self.commands.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Acknowledged records stay in the file until nothing is left to replay. With a steady
+// backlog that never happens, so the file is rewritten with only the live records once it
+// grows past the limit and is mostly acknowledged records.
+struct JournalState {
+    path: String,
+    journal: File,
+    live: BTreeMap<u64, JournalRecord>,
+    live_size: usize,
+    size: usize,
+    compact_size: usize,
+}
+
+impl JournalState {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<()> {
+        if self.live.is_empty() {
+            self.journal.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        self.journal.write_all(buffer)?;
+        self.journal.sync_data()?;
+        self.size += buffer.len();
+
+        if self.size > self.compact_size && self.size > self.live_size * 2 {
+            let records: Vec<_> = self.live.values().cloned().collect();
+            self.journal = rewrite_journal(&self.path, &records)?;
+            self.size = self.live_size;
+        }
+
+        Ok(())
+    }
+}
+
+fn run_writer(mut state: JournalState, receiver: Receiver<JournalCommand>) {
+    while let Ok(command) = receiver.recv() {
+        let mut buffer = Vec::new();
+        let mut waiters = Vec::new();
+
+        for command in std::iter::once(command).chain(receiver.try_iter()) {
+            match command {
+                JournalCommand::Append { record, written } => {
+                    encode_record(&mut buffer, record.kind, record.id, &record.payload);
+                    state.live_size += get_record_size(&record);
+                    state.live.insert(record.id, record);
+                    waiters.push(written);
+                }
+                JournalCommand::Ack(id) => {
+                    encode_record(&mut buffer, ACK_RECORD_KIND, id, &[]);
+                    if let Some(record) = state.live.remove(&id) {
+                        state.live_size -= get_record_size(&record);
+                    }
+                }
+            }
+        }
+
+        if let Err(err) = state.write(&buffer) {
+            service_sdk::metrics::counter!("outbox_journal_errors").increment(1);
+            println!("Failed to write outbox journal: {:?}", err);
+        }
+
+        for written in waiters {
+            let _ = written.send(());
+        }
+    }
+}
+
+fn get_record_size(record: &JournalRecord) -> usize {
+    RECORD_HEADER_SIZE + record.payload.len()
+}
+
+fn encode_record(buffer: &mut Vec<u8>, kind: u8, id: u64, payload: &[u8]) {
+    buffer.push(kind);
+    buffer.extend_from_slice(&id.to_le_bytes());
+    buffer.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(payload);
+}
+
+pub fn read_journal(path: &str) -> Vec<JournalRecord> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return vec![],
+        Err(err) => panic!("Can not read outbox journal {}: {:?}", path, err),
+    };
+
+    let mut records = BTreeMap::new();
+    let mut offset = 0;
+
+    // A record cut short by a crash is the last one in the file and was never acknowledged
+    // to the caller, so it is safe to stop there.
+    while offset + RECORD_HEADER_SIZE <= content.len() {
+        let kind = content[offset];
+        let id = u64::from_le_bytes(content[offset + 1..offset + 9].try_into().unwrap());
+        let len = u32::from_le_bytes(content[offset + 9..offset + 13].try_into().unwrap()) as usize;
+
+        let payload_start = offset + RECORD_HEADER_SIZE;
+        if payload_start + len > content.len() {
+            break;
+        }
+
+        if kind == ACK_RECORD_KIND {
+            records.remove(&id);
+        } else {
+            records.insert(
+                id,
+                JournalRecord {
+                    id,
+                    kind,
+                    payload: content[payload_start..payload_start + len].to_vec(),
+                },
+            );
+        }
+
+        offset = payload_start + len;
+    }
+
+    return records.into_values().collect();
+}
+
+fn rewrite_journal(path: &str, records: &[JournalRecord]) -> std::io::Result<File> {
+    let tmp_path = format!("{}.tmp", path);
+
+    let mut buffer = Vec::new();
+    for record in records {
+        encode_record(&mut buffer, record.kind, record.id, &record.payload);
+    }
+
+    {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&buffer)?;
+        tmp.sync_data()?;
+    }
+
+    std::fs::rename(&tmp_path, path)?;
+
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_journal, JournalRecord, JournalWriter, RECORD_HEADER_SIZE};
+
+    fn create_record(id: u64) -> JournalRecord {
+        JournalRecord {
+            id,
+            kind: 1,
+            payload: vec![0; 10],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compacts_acked_records() {
+        let path = std::env::temp_dir().join(format!("outbox-{}.journal", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        {
+            let writer = JournalWriter::start(path, &[], 1);
+            writer.append(create_record(1)).await.unwrap();
+            writer.append(create_record(2)).await.unwrap();
+            writer.ack(1);
+        }
+
+        let ids: Vec<_> = read_journal(path).iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(
+            std::fs::metadata(path).unwrap().len() as usize,
+            RECORD_HEADER_SIZE + 10
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+mod journal_writer;
+mod outbox_message;
+mod persistence_outbox;
+
+pub use outbox_message::*;
+pub use persistence_outbox::*;
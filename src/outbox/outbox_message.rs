@@ -0,0 +1,56 @@
+use cfd_engine_sb_contracts::{
+    PendingPositionPersistenceEvent, PositionPersistenceEvent, PositionToppingUpEvent,
+};
+use prost::Message;
+
+const ACTIVE_POSITION_KIND: u8 = 1;
+const PENDING_POSITION_KIND: u8 = 2;
+const TOPPING_UP_KIND: u8 = 3;
+
+#[derive(Debug, Clone)]
+pub enum OutboxMessage {
+    ActivePosition(PositionPersistenceEvent),
+    PendingPosition(PendingPositionPersistenceEvent),
+    ToppingUp(PositionToppingUpEvent),
+}
+
+impl OutboxMessage {
+    pub fn get_kind(&self) -> u8 {
+        match self {
+            OutboxMessage::ActivePosition(_) => ACTIVE_POSITION_KIND,
+            OutboxMessage::PendingPosition(_) => PENDING_POSITION_KIND,
+            OutboxMessage::ToppingUp(_) => TOPPING_UP_KIND,
+        }
+    }
+
+    pub fn get_process_id(&self) -> &str {
+        match self {
+            OutboxMessage::ActivePosition(src) => &src.process_id,
+            OutboxMessage::PendingPosition(src) => &src.process_id,
+            OutboxMessage::ToppingUp(src) => &src.process_id,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            OutboxMessage::ActivePosition(src) => src.encode_to_vec(),
+            OutboxMessage::PendingPosition(src) => src.encode_to_vec(),
+            OutboxMessage::ToppingUp(src) => src.encode_to_vec(),
+        }
+    }
+
+    pub fn decode(kind: u8, payload: &[u8]) -> Option<Self> {
+        match kind {
+            ACTIVE_POSITION_KIND => PositionPersistenceEvent::decode(payload)
+                .ok()
+                .map(OutboxMessage::ActivePosition),
+            PENDING_POSITION_KIND => PendingPositionPersistenceEvent::decode(payload)
+                .ok()
+                .map(OutboxMessage::PendingPosition),
+            TOPPING_UP_KIND => PositionToppingUpEvent::decode(payload)
+                .ok()
+                .map(OutboxMessage::ToppingUp),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+
+use service_sdk::my_telemetry::MyTelemetryContext;
+use tokio::sync::{Mutex, Notify};
+
+use super::{
+    journal_writer::{read_journal, JournalRecord, JournalWriter},
+    OutboxMessage,
+};
+
+#[derive(Clone)]
+pub struct OutboxItem {
+    pub id: u64,
+    pub message: OutboxMessage,
+    pub telemetry: Option<MyTelemetryContext>,
+}
+
+struct OutboxQueue {
+    next_id: u64,
+    items: VecDeque<OutboxItem>,
+    journal: Option<JournalWriter>,
+}
+
+// Enqueue returns only after the message is in the journal, and a message is acknowledged
+// only after the bus accepted it. On restart everything not acknowledged is loaded back
+// into the queue.
+pub struct PersistenceOutbox {
+    queue: Mutex<OutboxQueue>,
+    new_message: Notify,
+}
+
+impl PersistenceOutbox {
+    pub fn new(journal_path: Option<&str>) -> Self {
+        let mut items = VecDeque::new();
+        let mut next_id = 1;
+
+        let journal = journal_path.map(|path| {
+            let mut records = read_journal(path);
+
+            records.retain(
+                |record| match OutboxMessage::decode(record.kind, &record.payload) {
+                    Some(message) => {
+                        next_id = record.id + 1;
+                        items.push_back(OutboxItem {
+                            id: record.id,
+                            message,
+                            telemetry: None,
+                        });
+                        true
+                    }
+                    None => {
+                        println!("Skipping unreadable outbox journal record {}", record.id);
+                        false
+                    }
+                },
+            );
+
+            JournalWriter::new(path, &records)
+        });
+
+        if items.len() > 0 {
+            println!("Restored {} outbox messages from journal", items.len());
+        }
+
+        update_queue_size_metric(items.len());
+
+        Self {
+            queue: Mutex::new(OutboxQueue {
+                next_id,
+                items,
+                journal,
+            }),
+            new_message: Notify::new(),
+        }
+    }
+
+    pub async fn enqueue(&self, message: OutboxMessage, telemetry: Option<&MyTelemetryContext>) {
+        let written = {
+            let mut queue = self.queue.lock().await;
+            let id = queue.next_id;
+            queue.next_id += 1;
+
+            let written = queue.journal.as_ref().map(|journal| {
+                journal.append(JournalRecord {
+                    id,
+                    kind: message.get_kind(),
+                    payload: message.encode(),
+                })
+            });
+
+            queue.items.push_back(OutboxItem {
+                id,
+                message,
+                telemetry: telemetry.cloned(),
+            });
+
+            update_queue_size_metric(queue.items.len());
+
+            written
+        };
+
+        self.new_message.notify_one();
+
+        if let Some(written) = written {
+            let _ = written.await;
+        }
+    }
+
+    pub async fn peek(&self) -> Option<OutboxItem> {
+        let queue = self.queue.lock().await;
+        return queue.items.front().cloned();
+    }
+
+    pub async fn peek_batch(&self, max_count: usize) -> Vec<OutboxItem> {
+        let queue = self.queue.lock().await;
+        return queue.items.iter().take(max_count).cloned().collect();
+    }
+
+    pub async fn ack(&self, id: u64) {
+        let mut queue = self.queue.lock().await;
+
+        match queue.items.front() {
+            Some(item) if item.id == id => {}
+            _ => return,
+        }
+
+        queue.items.pop_front();
+
+        if let Some(journal) = queue.journal.as_ref() {
+            journal.ack(id);
+        }
+
+        update_queue_size_metric(queue.items.len());
+    }
+
+    pub async fn wait_for_message(&self) {
+        self.new_message.notified().await;
+    }
+
+    pub async fn len(&self) -> usize {
+        self.queue.lock().await.items.len()
+    }
+}
+
+fn update_queue_size_metric(size: usize) {
+    service_sdk::metrics::gauge!("outbox_queue_size").set(size as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use cfd_engine_sb_contracts::PositionToppingUpEvent;
+
+    use super::PersistenceOutbox;
+    use crate::OutboxMessage;
+
+    fn topping_up_message(process_id: &str) -> OutboxMessage {
+        OutboxMessage::ToppingUp(PositionToppingUpEvent {
+            process_id: process_id.to_string(),
+            position_id: "position".to_string(),
+            trader_id: "trader".to_string(),
+            account_id: "account".to_string(),
+            delta: 10.0,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_restores_not_acked_messages() {
+        let path = std::env::temp_dir().join(format!("outbox-{}.journal", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        {
+            let outbox = PersistenceOutbox::new(Some(path));
+            outbox.enqueue(topping_up_message("first"), None).await;
+            outbox.enqueue(topping_up_message("second"), None).await;
+
+            let first = outbox.peek().await.unwrap();
+            outbox.ack(first.id).await;
+        }
+
+        let outbox = PersistenceOutbox::new(Some(path));
+        assert_eq!(outbox.len().await, 1);
+
+        let restored = outbox.peek().await.unwrap();
+        assert_eq!(restored.message.get_process_id(), "second");
+
+        outbox.ack(restored.id).await;
+        assert_eq!(outbox.len().await, 0);
+
+        drop(outbox);
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
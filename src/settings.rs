@@ -1,5 +1,9 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::TickFilterRules;
+
 service_sdk::macros::use_settings!();
 
 #[derive(
@@ -16,6 +20,19 @@ pub struct SettingsModel {
     pub persistence_url: String,
     pub seq_conn_string: String,
     pub my_telemetry: String,
+    pub outbox_journal_path: Option<String>,
+    pub idempotency_ttl_sec: Option<u64>,
+    pub netting_accounts: Option<Vec<String>>,
+    pub netting_account_groups: Option<Vec<String>>,
+    pub account_groups: Option<HashMap<String, String>>,
+    pub pending_confirmation_timeout_sec: Option<u64>,
+    pub pending_confirmation_timeout_action: Option<String>,
+    pub pending_confirmations_journal_path: Option<String>,
+    pub max_quote_age_sec: Option<u64>,
+    pub max_quote_age_sec_by_instrument: Option<HashMap<String, u64>>,
+    pub tick_filter: Option<TickFilterRules>,
+    pub tick_filter_by_instrument: Option<HashMap<String, TickFilterRules>>,
+    pub cross_margin_stop_out_level: Option<f64>,
 }
 
 #[async_trait::async_trait]
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use service_sdk::{
+    my_service_bus::abstractions::{
+        publisher::{MessageToPublish, MyServiceBusPublisher},
+        MyServiceBusPublisherClient, PublishError,
+    },
+    rust_extensions::{date_time::DateTimeAsMicroseconds, AppStates},
+};
+use tokio::sync::{Mutex, RwLock};
+use trading_sdk::mt_engine::{
+    get_pending_position_type, ActivePositionsCache, MtBidAsk, MtBidAskCache, MtPosition,
+    MtPositionActiveState, MtPositionActiveStateOpenData, MtPositionBaseData,
+    MtPositionPendingState, MtPositionSide, MtPositionSwaps, PendingPositionsCache,
+};
+
+use crate::{
+    AccountModes, AccountsCache, AppContext, IdempotencyCache, PendingConfirmationsJournal,
+    PendingExpirationsCache, PersistenceOutbox, PositionUpdatesHub, QuoteAgeLimits,
+    RejectPendingAction, TickFilter,
+};
+
+pub struct TestPublisherClient {}
+
+#[async_trait::async_trait]
+impl MyServiceBusPublisherClient for TestPublisherClient {
+    async fn publish_message(
+        &self,
+        _topic_id: &str,
+        _message: MessageToPublish,
+        _do_retry: bool,
+    ) -> Result<(), PublishError> {
+        Ok(())
+    }
+
+    async fn publish_messages(
+        &self,
+        _topic_id: &str,
+        _message: &[MessageToPublish],
+        _do_retry: bool,
+    ) -> Result<(), PublishError> {
+        Ok(())
+    }
+}
+
+fn create_test_publisher<T>() -> MyServiceBusPublisher<T> {
+    MyServiceBusPublisher::new(
+        "test".to_string(),
+        Arc::new(TestPublisherClient {}),
+        false,
+        service_sdk::my_logger::LOGGER.clone(),
+    )
+}
+
+pub fn create_test_app() -> Arc<AppContext> {
+    Arc::new(AppContext {
+        active_positions_cache: Arc::new(RwLock::new(ActivePositionsCache::new())),
+        pending_positions_cache: Arc::new(RwLock::new(PendingPositionsCache::new())),
+        pending_expirations: Mutex::new(PendingExpirationsCache::new()),
+        pending_confirmation_deadlines: Mutex::new(PendingExpirationsCache::new()),
+        pending_confirmations_journal: Mutex::new(PendingConfirmationsJournal::new(None)),
+        pending_confirmation_timeout: Duration::from_secs(30),
+        pending_confirmation_timeout_action: RejectPendingAction::Cancel,
+        pending_execute_to_confirm_positions: Arc::new(RwLock::new(PendingPositionsCache::new())),
+        active_prices_cache: Arc::new(RwLock::new(MtBidAskCache::new())),
+        quote_age_limits: QuoteAgeLimits::new(None, &HashMap::new()),
+        last_quote_dates: Mutex::new(HashMap::new()),
+        tick_filter: Mutex::new(TickFilter::new(Default::default(), HashMap::new())),
+        accounts_cache: Mutex::new(AccountsCache::new()),
+        cross_margin_stop_out_level: None,
+        position_updates_hub: Mutex::new(PositionUpdatesHub::new()),
+        app_states: Arc::new(AppStates::create_initialized()),
+        active_positions_persistence_publisher: create_test_publisher(),
+        pending_positions_persistence_publisher: create_test_publisher(),
+        margin_call_publisher: create_test_publisher(),
+        topping_up_publisher: create_test_publisher(),
+        pending_need_confirm_publisher: create_test_publisher(),
+        persistence_outbox: PersistenceOutbox::new(None),
+        idempotency_cache: IdempotencyCache::new(Duration::from_secs(60)),
+        account_modes: AccountModes::new(&[], &[], HashMap::new()),
+        debug: false,
+    })
+}
+
+pub fn create_test_bid_ask(bid: f64, ask: f64) -> MtBidAsk {
+    MtBidAsk {
+        asset_pair: "EURUSD".to_string(),
+        bid,
+        ask,
+        date: DateTimeAsMicroseconds::now(),
+        base: "EUR".to_string(),
+        quote: "USD".to_string(),
+    }
+}
+
+fn create_test_base_data(id: &str, trader_id: &str, account_id: &str) -> MtPositionBaseData {
+    MtPositionBaseData {
+        id: id.to_string(),
+        trader_id: trader_id.to_string(),
+        account_id: account_id.to_string(),
+        asset_pair: "EURUSD".to_string(),
+        side: MtPositionSide::Buy,
+        invest_amount: 100.0,
+        leverage: 10.0,
+        stop_out_percent: 90.0,
+        create_process_id: "create".to_string(),
+        crate_date: DateTimeAsMicroseconds::now(),
+        last_update_process_id: "create".to_string(),
+        last_update_date: DateTimeAsMicroseconds::now(),
+        collateral: "USD".to_string(),
+        base: "EUR".to_string(),
+        quote: "USD".to_string(),
+        tp_profit: None,
+        tp_price: None,
+        sl_profit: None,
+        sl_price: None,
+        topping_up_percent: Some(10.0),
+        metadata: None,
+        margin_call_percent: Some(50.0),
+    }
+}
+
+pub fn create_test_active_position(
+    id: &str,
+    trader_id: &str,
+    account_id: &str,
+) -> MtPosition<MtPositionActiveState> {
+    let bid_ask = create_test_bid_ask(1.1, 1.1);
+
+    MtPosition {
+        base_data: create_test_base_data(id, trader_id, account_id),
+        state: MtPositionActiveState {
+            open_data: MtPositionActiveStateOpenData {
+                asset_open_price: bid_ask.ask,
+                asset_open_bid_ask: bid_ask.clone(),
+                base_collateral_open_price: 1.0,
+                base_collateral_open_bid_ask: None,
+                open_process_id: "open".to_string(),
+                open_date: DateTimeAsMicroseconds::now(),
+                pending_state: None,
+            },
+            asset_active_price: bid_ask.bid,
+            asset_active_bid_ask: bid_ask,
+            quote_collateral_active_price: 1.0,
+            quote_collateral_active_bid_ask: None,
+            profit: 0.0,
+            swaps: MtPositionSwaps {
+                swaps: vec![],
+                total: 0.0,
+            },
+            topping_up: None,
+            is_margin_call_hit: false,
+        },
+    }
+}
+
+pub fn create_test_pending_position(
+    id: &str,
+    trader_id: &str,
+    account_id: &str,
+) -> MtPosition<MtPositionPendingState> {
+    let base_data = create_test_base_data(id, trader_id, account_id);
+    let desire_price = 1.0;
+
+    MtPosition {
+        state: MtPositionPendingState {
+            desire_price,
+            position_type: get_pending_position_type(1.1, desire_price, &base_data.side),
+        },
+        base_data,
+    }
+}
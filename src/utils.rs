@@ -1,3 +1,35 @@
+use trading_sdk::mt_engine::{MtBidAsk, MtPositionSide};
+
+pub fn get_open_price(bid_ask: &MtBidAsk, side: &MtPositionSide) -> f64 {
+    match side {
+        MtPositionSide::Buy => bid_ask.ask,
+        MtPositionSide::Sell => bid_ask.bid,
+    }
+}
+
+pub fn get_opposite_side(side: &MtPositionSide) -> MtPositionSide {
+    match side {
+        MtPositionSide::Buy => MtPositionSide::Sell,
+        MtPositionSide::Sell => MtPositionSide::Buy,
+    }
+}
+
+// A stop loss may only move towards the profitable side of the position.
+pub fn is_sl_improved(side: &MtPositionSide, sl_price: f64, current: Option<f64>) -> bool {
+    match (side, current) {
+        (_, None) => true,
+        (MtPositionSide::Buy, Some(current)) => sl_price > current,
+        (MtPositionSide::Sell, Some(current)) => sl_price < current,
+    }
+}
+
+pub fn is_same_side(left: &MtPositionSide, right: &MtPositionSide) -> bool {
+    matches!(
+        (left, right),
+        (MtPositionSide::Buy, MtPositionSide::Buy) | (MtPositionSide::Sell, MtPositionSide::Sell)
+    )
+}
+
 // use std::sync::Arc;
 
 // use crate::caches::ExecutionBidAsk;